@@ -0,0 +1,35 @@
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+// Must match the layout expected by src/board/zobrist_hash.rs
+const NUM_SQUARES: usize = 64;
+const NUM_PIECES: usize = 12;
+const NUM_CASTLING_RIGHTS: usize = 16;
+const NUM_EN_PASSANT_SQUARES: usize = 8;
+const ZOBRIST_SEED: u64 = 0x123456789ABCDEF;
+
+// Writes the zobrist keys as statics included by the board, drawn in a fixed order from a seeded
+// generator so hashes are the same from build to build
+fn main() {
+    let mut rng = ChaCha8Rng::seed_from_u64(ZOBRIST_SEED);
+    let mut keys = |count: usize| (0..count).map(|_| format!("0x{:016x}", rng.gen::<u64>())).collect::<Vec<_>>().join(", ");
+
+    let mut source = String::new();
+    writeln!(source, "pub static ZOBRIST_SQUARES: [[u64; NUM_PIECES]; NUM_SQUARES] = [").unwrap();
+    for _ in 0..NUM_SQUARES {
+        writeln!(source, "    [{}],", keys(NUM_PIECES)).unwrap();
+    }
+    writeln!(source, "];").unwrap();
+    writeln!(source, "pub static ZOBRIST_SIDE: u64 = {};", keys(1)).unwrap();
+    writeln!(source, "pub static ZOBRIST_CASTLING_RIGHTS: [u64; NUM_CASTLING_RIGHTS] = [{}];", keys(NUM_CASTLING_RIGHTS)).unwrap();
+    writeln!(source, "pub static ZOBRIST_EN_PASSANT_SQUARE: [u64; NUM_EN_PASSANT_SQUARES] = [{}];", keys(NUM_EN_PASSANT_SQUARES)).unwrap();
+
+    let path = Path::new(&env::var("OUT_DIR").unwrap()).join("zobrist_keys.rs");
+    fs::write(path, source).unwrap();
+    println!("cargo::rerun-if-changed=build.rs");
+}
@@ -1,9 +1,10 @@
 use std::time::Duration;
 
-use crate::{board::Board, search::{search, Search, SearchMode, SearchParams}};
+use crate::search::listener::SilentListener;
+use crate::{board::Board, search::{Search, SearchMode, SearchParams}};
 
 /// Positions used when running engine benchmarks.
-const BENCHMARK_FENS: [&str; 56] = [
+pub(crate) const BENCHMARK_FENS: [&str; 56] = [
     // From [Stormphrax](https://github.com/Ciekce/Stormphrax/blob/correct_ep_handling/src/bench.cpp#L29).
     "q5k1/5ppp/1r3bn1/1B6/P1N2P2/BQ2P1P1/5K1P/8 b - - 2 34",
     "6r1/5k2/p1b1r2p/1pB1p1p1/1Pp3PP/2P1R1K1/2P2P2/3R4 w - - 1 36",
@@ -79,25 +80,40 @@ const BENCHMARK_FENS: [&str; 56] = [
     "1B1rN3/p6q/5pp1/PPk4b/R1P1Pp1r/2pP1Bn1/2N1P2p/4KR2 w - - 0 1",
     "4r1k1/8/2p5/2p1Rpq1/6P1/1PP4Q/P5K1/RN1r4 b - - 0 1",
 ];
-const BENCHMARK_DEPTH: u32 = 5;
+pub const BENCHMARK_DEPTH: u32 = 5;
+pub const BENCHMARK_HASH_MB: usize = 16;
 
-pub fn bench() {
+// Searches every benchmark position to a fixed depth from an empty transposition table and
+// history, so the node count only changes with the search itself. Ends with the lines OpenBench
+// reads the node count and speed from.
+pub fn bench(depth: u32, hash_mb: usize, quiet: bool) {
+    let mut search = Search::default();
+    search.resize_hash(hash_mb);
+    if quiet {
+        search.listener = Box::new(SilentListener);
+    }
     let mut time = Duration::ZERO;
-    let mut nodes = 0;
+    let mut nodes: u64 = 0;
     for fen in BENCHMARK_FENS {
         let mut board = Board::from_fen(fen);
-        let mut search = Search::default();
+        search.new_game();
         let search_params = SearchParams {
-            depth: Some(BENCHMARK_DEPTH),
+            depth: Some(depth),
             search_mode: SearchMode::Infinite,
+            use_book: false,
             ..Default::default()
         };
-        println!("fen: {}", fen);
+        if !quiet {
+            println!("fen: {}", fen);
+        }
         let result = search.search(search_params, &mut board);
         time += result.time;
-        nodes += result.nodes;
-        println!();
+        nodes += result.nodes as u64;
+        if !quiet {
+            println!();
+        }
     }
-    let nps = ((nodes as f64 / time.as_nanos() as f64) * 1e9) as u128;
-    println!("{} nodes / {}s := {} nps", nodes, time.as_secs(), nps);
+    let nps = (nodes as f64 / time.as_secs_f64().max(1e-9)) as u64;
+    println!("Nodes searched: {}", nodes);
+    println!("Nodes/second: {}", nps);
 }
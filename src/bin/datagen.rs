@@ -0,0 +1,140 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use chess_engine::board::Board;
+use chess_engine::datagen::{play_game, DatagenConfig, GameResult};
+use chess_engine::evaluation::kpk;
+use chess_engine::evaluation::nnue::Network;
+use chess_engine::search::listener::SilentListener;
+use chess_engine::search::transposition_table::TranspositionTable;
+use chess_engine::search::Search;
+use rand::SeedableRng;
+
+const DEFAULT_GAMES: usize = 1000;
+const REPORT_INTERVAL: usize = 100;
+
+#[derive(Default)]
+struct Stats {
+    games: usize,
+    positions: usize,
+    results: [usize; 3], // White wins, draws and black wins
+}
+
+// Self-play games for training the network and tuning the evaluation.
+//
+// Usage: datagen <output> [games] [--threads <n>] [--nodes <n>] [--seed <n>] [--binary] [--network <file>]
+//
+// Games start from a few random moves, game i always gets the same ones for a given seed. Every
+// quiet position is written as a `<fen> | <score> | <result>` line from white's point of view, or
+// as a 32 byte packed board from the side to move's point of view with --binary. The threads share
+// the transposition table, so the games themselves only repeat exactly with a single thread.
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut positional = Vec::new();
+    let mut config = DatagenConfig::default();
+    let mut threads = 1;
+    let mut seed = 0;
+    let mut binary = false;
+    let mut network = None;
+    let mut args_iter = args.iter().skip(1);
+    while let Some(arg) = args_iter.next() {
+        let mut value = |name: &str| args_iter.next().cloned().unwrap_or_else(|| exit_with_usage(&format!("{} needs a value", name)));
+        match arg.as_str() {
+            "--threads" => threads = parse_arg("--threads", &value("--threads")),
+            "--nodes" => config.nodes = parse_arg("--nodes", &value("--nodes")),
+            "--seed" => seed = parse_arg("--seed", &value("--seed")),
+            "--network" => network = Some(Arc::new(Network::load(&value("--network")).unwrap_or_else(|error| exit_with_error(&error)))),
+            "--binary" => binary = true,
+            _ => positional.push(arg.as_str()),
+        }
+    }
+    let [output_path, rest @ ..] = positional.as_slice() else {
+        exit_with_usage("expected an output file");
+    };
+    let games: usize = rest.first().map_or(DEFAULT_GAMES, |games| parse_arg("games", games));
+
+    kpk::initialize();
+    let output = Mutex::new(BufWriter::new(File::create(output_path).unwrap_or_else(|error| exit_with_error(&format!("{}: {}", output_path, error)))));
+    let stats = Mutex::new(Stats::default());
+    let next_game = AtomicUsize::new(0);
+    let transposition_table = Arc::new(TranspositionTable::new()); // Shared by the threads
+    let start = Instant::now();
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                let mut search = Search::with_transposition_table(transposition_table.clone());
+                search.listener = Box::new(SilentListener);
+                loop {
+                    let game_index = next_game.fetch_add(1, Ordering::Relaxed);
+                    if game_index >= games {
+                        break;
+                    }
+                    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(seed);
+                    rng.set_stream(game_index as u64);
+                    let mut board = Board::start_pos();
+                    board.set_network(network.clone());
+                    // Openings that end early or are one-sided are skipped
+                    let Some(game) = play_game(&mut search, &mut board, &mut rng, &config) else { continue };
+
+                    let mut output = output.lock().unwrap();
+                    let written = if binary { game.write_binary(&mut *output) } else { game.write_text(&mut *output) };
+                    if let Err(error) = written {
+                        exit_with_error(&format!("{}: {}", output_path, error));
+                    }
+                    drop(output);
+
+                    let mut stats = stats.lock().unwrap();
+                    stats.games += 1;
+                    stats.positions += game.samples.len();
+                    stats.results[game.result as usize] += 1;
+                    if stats.games % REPORT_INTERVAL == 0 {
+                        report(&stats, start);
+                    }
+                }
+            });
+        }
+    });
+
+    if let Err(error) = output.into_inner().unwrap().flush() {
+        exit_with_error(&format!("{}: {}", output_path, error));
+    }
+    report(&stats.into_inner().unwrap(), start);
+    println!("Positions written to {}", output_path);
+}
+
+fn exit_with_error(error: &str) -> ! {
+    eprintln!("{}", error);
+    std::process::exit(1);
+}
+
+fn exit_with_usage(error: &str) -> ! {
+    eprintln!("{}", error);
+    eprintln!("usage: datagen <output> [games] [--threads <n>] [--nodes <n>] [--seed <n>] [--binary] [--network <file>]");
+    std::process::exit(1);
+}
+
+fn parse_arg<T: std::str::FromStr>(name: &str, value: &str) -> T {
+    value.parse().unwrap_or_else(|_| exit_with_usage(&format!("invalid value for {}: {}", name, value)))
+}
+
+fn report(stats: &Stats, start: Instant) {
+    let seconds = start.elapsed().as_secs_f32();
+    let [white, draws, black] = stats.results;
+    println!(
+        "{} games, {} positions ({:.0}/s), +{} ={} -{}",
+        stats.games,
+        stats.positions,
+        stats.positions as f32 / seconds,
+        white,
+        draws,
+        black
+    );
+}
+
+// Keeps the result order of the counters in sync with the enum
+const _: () = assert!(GameResult::WhiteWin as usize == 0 && GameResult::Draw as usize == 1 && GameResult::BlackWin as usize == 2);
@@ -0,0 +1,33 @@
+use chess_engine::board::piece::PieceType;
+use chess_engine::move_generation::magic_finder::{find_magics, magic_numbers_source};
+use rand::SeedableRng;
+
+// Magics tried per square, keeping the one that leaves the most of the end of the square's table
+// empty for the next one. Rook magics are rarely that lucky and take longer to find.
+const ROOK_CANDIDATES: usize = 200;
+const BISHOP_CANDIDATES: usize = 20000;
+
+// Searches magics for the slider attack tables and prints them as the source of magic_numbers.rs.
+//
+// Usage: magics [--seed <n>]
+//
+// The same seed always finds the same magics, taking a few minutes. The sizes of the tables are
+// written to stderr.
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let seed = match args.get(1..).unwrap_or_default() {
+        [] => 0,
+        [flag, seed] if flag == "--seed" => seed.parse().expect("invalid seed"),
+        _ => {
+            eprintln!("usage: magics [--seed <n>]");
+            return;
+        }
+    };
+
+    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(seed);
+    let rook = find_magics(PieceType::Rook, ROOK_CANDIDATES, &mut rng);
+    let bishop = find_magics(PieceType::Bishop, BISHOP_CANDIDATES, &mut rng);
+    eprintln!("rook table: {} entries, {} KiB", rook.size, rook.size * 8 / 1024);
+    eprintln!("bishop table: {} entries, {} KiB", bishop.size, bishop.size * 8 / 1024);
+    print!("{}", magic_numbers_source(&rook, &bishop));
+}
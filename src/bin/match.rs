@@ -0,0 +1,578 @@
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chess_engine::board::piece_move::Move;
+use chess_engine::board::{Board, Side, STARTING_FEN};
+use chess_engine::datagen::{game_over, Adjudication, GameResult, ScoreTracker};
+use chess_engine::search::search::USE_SYZYGY;
+use chess_engine::search::Search;
+use chess_engine::sprt::{MatchResults, Sprt};
+use pyrrhic_rs::WdlProbeResult;
+
+const DEFAULT_ROUNDS: usize = 100;
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+const TIME_MARGIN: u128 = 100; // Milliseconds an engine may go over its clock
+const MATE_SCORE: i32 = 100000;
+const USAGE: &str = "usage: match <engine1> <engine2> [--openings <epd or pgn>] [--tc 10+0.1] [--rounds <n>] [--concurrency <n>] [--pgn <file>] [--sprt <elo0> <elo1>] [--option <name>=<value>]";
+
+// Plays paired games between two UCI engines, every opening once with each engine as white.
+//
+// Usage: match <engine1> <engine2> [--openings <epd or pgn>] [--tc 10+0.1] [--rounds <n>]
+//              [--concurrency <n>] [--pgn <file>] [--sprt <elo0> <elo1>] [--option <name>=<value>]
+//
+// The time control is seconds per game plus seconds of increment per move. Games are adjudicated
+// by the rules, by the tablebases when built with the syzygy feature, and by the scores the
+// engines report. Results are from the first engine's point of view, and the match stops early
+// once the SPRT accepts either hypothesis.
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut positional = Vec::new();
+    let mut openings_path = None;
+    let mut time_control = TimeControl { time: 10000, increment: 100 };
+    let mut rounds = DEFAULT_ROUNDS;
+    let mut concurrency = 1;
+    let mut pgn_path = None;
+    let mut sprt = None;
+    let mut options = Vec::new();
+    let mut args_iter = args.iter().skip(1);
+    while let Some(arg) = args_iter.next() {
+        let mut value = |name: &str| args_iter.next().cloned().unwrap_or_else(|| exit_with_usage(&format!("{} needs a value", name)));
+        match arg.as_str() {
+            "--openings" => openings_path = Some(value("--openings")),
+            "--tc" => {
+                let value = value("--tc");
+                time_control = TimeControl::parse(&value).unwrap_or_else(|| exit_with_usage(&format!("invalid time control: {}", value)));
+            }
+            "--rounds" => rounds = parse_arg("--rounds", &value("--rounds")),
+            "--concurrency" => concurrency = parse_arg("--concurrency", &value("--concurrency")),
+            "--pgn" => pgn_path = Some(value("--pgn")),
+            "--sprt" => sprt = Some(Sprt::new(parse_arg("--sprt", &value("--sprt")), parse_arg("--sprt", &value("--sprt")))),
+            "--option" => {
+                let option = value("--option");
+                let Some((name, value)) = option.split_once('=') else {
+                    exit_with_usage(&format!("options are written as <name>=<value>: {}", option));
+                };
+                options.push((name.to_string(), value.to_string()));
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+    let [first, second] = positional.as_slice() else {
+        exit_with_usage("expected two engines");
+    };
+    let openings = match &openings_path {
+        Some(path) => load_openings(path).unwrap_or_else(|error| exit_with_error(&format!("{}: {}", path, error))),
+        None => vec![Opening { fen: STARTING_FEN.to_string(), moves: Vec::new() }],
+    };
+    if openings.is_empty() {
+        exit_with_error("no openings found");
+    }
+
+    let commands = [first.as_str(), second.as_str()];
+    let start_engine = |index: usize| EngineProcess::start(commands[index], &options).unwrap_or_else(|error| exit_with_error(&format!("{}: {}", commands[index], error)));
+    let engine_pairs: Vec<[EngineProcess; 2]> = (0..concurrency).map(|_| [start_engine(0), start_engine(1)]).collect();
+    // Names the engines give themselves, told apart when they are the same
+    let mut names = engine_pairs[0].each_ref().map(|engine| engine.name.clone());
+    if names[0] == names[1] {
+        names[1].push_str(" (2)");
+    }
+    println!("{} vs {}, {} rounds of 2 games at {}", names[0], names[1], rounds, time_control);
+
+    let pgn = pgn_path.as_ref().map(|path| Mutex::new(BufWriter::new(File::create(path).unwrap_or_else(|error| exit_with_error(&format!("{}: {}", path, error))))));
+    let results = Mutex::new(MatchResults::default());
+    let next_round = AtomicUsize::new(0);
+    let stopped = AtomicBool::new(false);
+
+    let (openings, names, results, next_round, stopped, pgn, start_engine, time_control) = (&openings, &names, &results, &next_round, &stopped, &pgn, &start_engine, &time_control);
+    thread::scope(|scope| {
+        for mut engines in engine_pairs {
+            scope.spawn(move || {
+                let tablebases = USE_SYZYGY.then(Search::default);
+                loop {
+                    let round = next_round.fetch_add(1, Ordering::Relaxed);
+                    if round >= rounds || stopped.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let opening = &openings[round % openings.len()];
+                    let mut scores = [0.0; 2];
+                    for (game, score) in scores.iter_mut().enumerate() {
+                        // The first engine is white in the first game of a pair and black in the second
+                        let record = {
+                            let [first, second] = &mut engines;
+                            let players = if game == 0 { [first, second] } else { [second, first] };
+                            play_game(players, opening, time_control, tablebases.as_ref())
+                        };
+                        *score = match (record.result, game) {
+                            (GameResult::Draw, _) => 0.5,
+                            (GameResult::WhiteWin, 0) | (GameResult::BlackWin, 1) => 1.0,
+                            _ => 0.0,
+                        };
+                        if let Some(pgn) = &pgn {
+                            let players = if game == 0 { [&names[0], &names[1]] } else { [&names[1], &names[0]] };
+                            let text = record.pgn(players, &format!("{}.{}", round + 1, game + 1), time_control);
+                            let mut pgn = pgn.lock().unwrap();
+                            pgn.write_all(text.as_bytes()).and_then(|_| pgn.flush()).expect("failed to write the PGN");
+                        }
+                        // Engines that crashed or hung are replaced for the next game
+                        for (index, engine) in engines.iter_mut().enumerate() {
+                            if !engine.alive {
+                                *engine = start_engine(index);
+                            }
+                        }
+                    }
+
+                    let mut results = results.lock().unwrap();
+                    results.add_pair(scores);
+                    if report(&results, sprt.as_ref()) {
+                        stopped.store(true, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+    });
+}
+
+fn exit_with_error(error: &str) -> ! {
+    eprintln!("{}", error);
+    std::process::exit(1);
+}
+
+fn exit_with_usage(error: &str) -> ! {
+    eprintln!("{}", error);
+    eprintln!("{}", USAGE);
+    std::process::exit(1);
+}
+
+fn parse_arg<T: std::str::FromStr>(name: &str, value: &str) -> T {
+    value.parse().unwrap_or_else(|_| exit_with_usage(&format!("invalid value for {}: {}", name, value)))
+}
+
+// Prints the results so far, returning whether the SPRT has finished
+fn report(results: &MatchResults, sprt: Option<&Sprt>) -> bool {
+    let mut line = format!("Games {}: +{} ={} -{}", results.games(), results.wins, results.draws, results.losses);
+    match results.elo() {
+        Some((elo, margin)) => line.push_str(&format!(", Elo {:.1} +- {:.1}", elo, margin)),
+        None => line.push_str(", Elo -"),
+    }
+    let mut finished = false;
+    if let Some(sprt) = sprt {
+        let (lower, upper) = sprt.bounds();
+        match results.llr(sprt) {
+            Some(llr) => {
+                line.push_str(&format!(", LLR {:.2} ({:.2}, {:.2}) [{}, {}]", llr, lower, upper, sprt.elo0, sprt.elo1));
+                if llr >= upper {
+                    line.push_str(", H1 accepted");
+                    finished = true;
+                } else if llr <= lower {
+                    line.push_str(", H0 accepted");
+                    finished = true;
+                }
+            }
+            None => line.push_str(", LLR -"),
+        }
+    }
+    println!("{}", line);
+    finished
+}
+
+struct TimeControl {
+    time: u128,      // Milliseconds per game
+    increment: u128, // Milliseconds added after every move
+}
+
+impl TimeControl {
+    // Seconds per game with an optional increment, like `10+0.1` or `tc=60`
+    fn parse(string: &str) -> Option<Self> {
+        let string = string.strip_prefix("tc=").unwrap_or(string);
+        let (time, increment) = string.split_once('+').unwrap_or((string, "0"));
+        let milliseconds = |seconds: &str| seconds.parse::<f64>().ok().filter(|seconds| *seconds >= 0.0).map(|seconds| (seconds * 1000.0).round() as u128);
+        Some(Self { time: milliseconds(time)?, increment: milliseconds(increment)? })
+    }
+}
+
+impl std::fmt::Display for TimeControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}+{}", self.time as f64 / 1000.0, self.increment as f64 / 1000.0)
+    }
+}
+
+/// Finished game with everything needed to write it as PGN.
+struct GameRecord {
+    fen: String,
+    moves: Vec<Move>, // Including the opening moves
+    result: GameResult,
+    termination: Option<&'static str>, // Why the game ended, unless by the rules
+}
+
+impl GameRecord {
+    fn pgn(&self, players: [&String; 2], round: &str, time_control: &TimeControl) -> String {
+        let result = match self.result {
+            GameResult::WhiteWin => "1-0",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::BlackWin => "0-1",
+        };
+        let mut pgn = String::new();
+        pgn.push_str("[Event \"match\"]\n[Site \"?\"]\n[Date \"????.??.??\"]\n");
+        pgn.push_str(&format!("[Round \"{}\"]\n[White \"{}\"]\n[Black \"{}\"]\n[Result \"{}\"]\n", round, players[0], players[1], result));
+        if self.fen != STARTING_FEN {
+            pgn.push_str(&format!("[FEN \"{}\"]\n[SetUp \"1\"]\n", self.fen));
+        }
+        pgn.push_str(&format!("[TimeControl \"{}\"]\n", time_control));
+        if let Some(termination) = self.termination {
+            pgn.push_str(&format!("[Termination \"{}\"]\n", termination));
+        }
+        pgn.push('\n');
+
+        let mut board = Board::from_fen(&self.fen);
+        let mut move_number: usize = self.fen.split_whitespace().nth(5).and_then(|number| number.parse().ok()).unwrap_or(1);
+        let mut tokens = Vec::new();
+        for (index, &mov) in self.moves.iter().enumerate() {
+            if board.side == Side::White {
+                tokens.push(format!("{}.", move_number));
+            } else {
+                if index == 0 {
+                    tokens.push(format!("{}...", move_number));
+                }
+                move_number += 1;
+            }
+            tokens.push(board.san(mov));
+            board.make_move(mov);
+        }
+        tokens.push(result.to_string());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > 80 {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        pgn.push_str(&line);
+        pgn.push_str("\n\n");
+        pgn
+    }
+}
+
+// Plays a game from the opening with the engines as white and black
+fn play_game(mut players: [&mut EngineProcess; 2], opening: &Opening, time_control: &TimeControl, tablebases: Option<&Search>) -> GameRecord {
+    let mut board = Board::from_fen(&opening.fen);
+    let mut moves = opening.moves.clone();
+    for &mov in moves.iter() {
+        board.make_move(mov);
+    }
+    let (result, termination) = play_moves(&mut players, &mut board, &opening.fen, &mut moves, time_control, tablebases);
+    GameRecord { fen: opening.fen.clone(), moves, result, termination }
+}
+
+fn play_moves(players: &mut [&mut EngineProcess; 2], board: &mut Board, fen: &str, moves: &mut Vec<Move>, time_control: &TimeControl, tablebases: Option<&Search>) -> (GameResult, Option<&'static str>) {
+    for side in [Side::White, Side::Black] {
+        let player = &mut players[side];
+        if player.send("ucinewgame").and_then(|_| player.synchronize()).is_err() {
+            return (GameResult::win_for(side.enemy()), Some("abandoned"));
+        }
+    }
+
+    let mut clock = [time_control.time; 2];
+    let mut scores = ScoreTracker::new(Adjudication::default());
+    loop {
+        if let Some(result) = game_over(board) {
+            return (result, None);
+        }
+        if let Some(wdl) = tablebases.and_then(|tablebases| tablebases.probe_syzygy_wdl(board)) {
+            let result = match wdl {
+                WdlProbeResult::Win => GameResult::win_for(board.side),
+                WdlProbeResult::Loss => GameResult::win_for(board.side.enemy()),
+                _ => GameResult::Draw,
+            };
+            return (result, Some("adjudication"));
+        }
+
+        let side = board.side;
+        let mut position = format!("position fen {}", fen);
+        if !moves.is_empty() {
+            position.push_str(" moves");
+            for mov in moves.iter() {
+                position.push_str(&format!(" {}", mov));
+            }
+        }
+        let start = Instant::now();
+        let reply = players[side].go(&position, &clock, time_control.increment, clock[side]);
+        let elapsed = start.elapsed().as_millis();
+        if elapsed > clock[side] + TIME_MARGIN {
+            return (GameResult::win_for(side.enemy()), Some("time forfeit"));
+        }
+        let Ok((best_move, score)) = reply else {
+            return (GameResult::win_for(side.enemy()), Some("abandoned"));
+        };
+        clock[side] = clock[side].saturating_sub(elapsed) + time_control.increment;
+
+        let Some(mov) = board.parse_uci_move(&best_move).ok().filter(|&mov| mov != Move::NULL) else {
+            return (GameResult::win_for(side.enemy()), Some("rules infraction"));
+        };
+        board.make_move(mov);
+        moves.push(mov);
+
+        if let Some(score) = score {
+            let white_score = if side == Side::White { score } else { -score };
+            if let Some(result) = scores.update(white_score) {
+                return (result, Some("adjudication"));
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Opening {
+    fen: String,
+    moves: Vec<Move>,
+}
+
+// EPD files hold a position per line, PGN files a game whose moves are played as the opening
+fn load_openings(path: &str) -> Result<Vec<Opening>, String> {
+    let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+    if path.ends_with(".pgn") {
+        return parse_pgn(&text);
+    }
+    parse_epd(&text)
+}
+
+fn parse_epd(text: &str) -> Result<Vec<Opening>, String> {
+    let mut openings = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        // Operations like `bm` or `id` follow the first four fields
+        let fields: Vec<&str> = line.split_whitespace().take(4).collect();
+        let fen = format!("{} 0 1", fields.join(" "));
+        Board::try_from_fen(&fen).map_err(|error| format!("line {}: {}", index + 1, error))?;
+        openings.push(Opening { fen, moves: Vec::new() });
+    }
+    Ok(openings)
+}
+
+fn parse_pgn(text: &str) -> Result<Vec<Opening>, String> {
+    let mut openings = Vec::new();
+    let mut fen = STARTING_FEN.to_string();
+    let mut board = Board::from_fen(&fen);
+    let mut moves = Vec::new();
+    let mut depth = 0; // Nesting of comments and variations, whose moves are skipped
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if depth == 0 && line.starts_with('[') {
+            if let Some(value) = line.strip_prefix("[FEN \"").and_then(|rest| rest.strip_suffix("\"]")) {
+                fen = value.to_string();
+                board = Board::try_from_fen(&fen).map_err(|error| format!("line {}: {}", index + 1, error))?;
+            }
+            continue;
+        }
+        let line = line.replace('{', " { ").replace('}', " } ").replace('(', " ( ").replace(')', " ) ");
+        for token in line.split_whitespace() {
+            match token {
+                "{" | "(" => depth += 1,
+                "}" | ")" => depth -= 1,
+                _ if depth > 0 => {}
+                _ if token.starts_with(';') => break,
+                "1-0" | "0-1" | "1/2-1/2" | "*" => {
+                    openings.push(Opening { fen, moves: std::mem::take(&mut moves) });
+                    fen = STARTING_FEN.to_string();
+                    board = Board::from_fen(&fen);
+                }
+                _ if token.starts_with('$') => {}
+                _ => {
+                    // Move numbers may be written right before the move, like `1.e4`
+                    let san = token.trim_start_matches(|char: char| char.is_ascii_digit() || char == '.');
+                    if san.is_empty() {
+                        continue;
+                    }
+                    let mov = board.parse_san(san).ok_or_else(|| format!("line {}: illegal move {} in {}", index + 1, san, board.fen()))?;
+                    board.make_move(mov);
+                    moves.push(mov);
+                }
+            }
+        }
+    }
+    Ok(openings)
+}
+
+/// UCI engine running as a child process.
+struct EngineProcess {
+    name: String,
+    process: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    alive: bool, // Cleared when the engine stops responding
+}
+
+impl EngineProcess {
+    fn start(command: &str, options: &[(String, String)]) -> Result<Self, String> {
+        let mut process = Command::new(command).stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::null()).spawn().map_err(|error| error.to_string())?;
+        let stdin = process.stdin.take().unwrap();
+        let stdout = process.stdout.take().unwrap();
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut engine = Self { name: command.to_string(), process, stdin, lines, alive: true };
+        engine.send("uci")?;
+        for line in engine.wait_for("uciok", STARTUP_TIMEOUT)? {
+            if let Some(name) = line.strip_prefix("id name ") {
+                // Only the first one, later ones are most likely meant as the author
+                if engine.name == command {
+                    engine.name = name.trim().to_string();
+                }
+            }
+        }
+        for (name, value) in options {
+            engine.send(&format!("setoption name {} value {}", name, value))?;
+        }
+        engine.synchronize()?;
+        Ok(engine)
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), String> {
+        let result = writeln!(self.stdin, "{}", command).and_then(|_| self.stdin.flush());
+        result.map_err(|error| {
+            self.alive = false;
+            error.to_string()
+        })
+    }
+
+    // Lines received up to and including the one starting with the token
+    fn wait_for(&mut self, token: &str, timeout: Duration) -> Result<Vec<String>, String> {
+        let deadline = Instant::now() + timeout;
+        let mut lines = Vec::new();
+        loop {
+            let line = self.lines.recv_timeout(deadline.saturating_duration_since(Instant::now())).map_err(|error| {
+                self.alive = false;
+                match error {
+                    RecvTimeoutError::Timeout => format!("no {} within {:?}", token, timeout),
+                    RecvTimeoutError::Disconnected => "engine exited".to_string(),
+                }
+            })?;
+            let found = line.split_whitespace().next() == Some(token);
+            lines.push(line);
+            if found {
+                return Ok(lines);
+            }
+        }
+    }
+
+    fn synchronize(&mut self) -> Result<(), String> {
+        self.send("isready")?;
+        self.wait_for("readyok", STARTUP_TIMEOUT).map(|_| ())
+    }
+
+    // Best move in UCI notation and the last reported score from the engine's point of view
+    fn go(&mut self, position: &str, clock: &[u128; 2], increment: u128, time_left: u128) -> Result<(String, Option<i32>), String> {
+        self.send(position)?;
+        self.send(&format!("go wtime {} btime {} winc {} binc {}", clock[Side::White], clock[Side::Black], increment, increment))?;
+        let lines = self.wait_for("bestmove", Duration::from_millis((time_left + TIME_MARGIN) as u64))?;
+        let best_move = lines.last().and_then(|line| line.split_whitespace().nth(1)).unwrap_or_default().to_string();
+        let score = lines.iter().rev().find_map(|line| {
+            let mut words = line.split_whitespace().skip_while(|&word| word != "score").skip(1);
+            match (words.next()?, words.next()?.parse::<i32>().ok()?) {
+                ("cp", centipawns) => Some(centipawns),
+                ("mate", moves) => Some(if moves > 0 { MATE_SCORE - moves } else { -MATE_SCORE - moves }),
+                _ => None,
+            }
+        });
+        Ok((best_move, score))
+    }
+}
+
+impl Drop for EngineProcess {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_time_controls() {
+        let time_control = TimeControl::parse("10+0.1").unwrap();
+        assert_eq!((time_control.time, time_control.increment), (10000, 100));
+        let time_control = TimeControl::parse("tc=60").unwrap();
+        assert_eq!((time_control.time, time_control.increment), (60000, 0));
+        assert!(TimeControl::parse("-1").is_none());
+        assert!(TimeControl::parse("10+x").is_none());
+    }
+
+    #[test]
+    fn parses_pgn_openings() {
+        let text = "[Event \"?\"]\n\
+                    1.e4 {a comment with (parentheses) in it} e5 (1... c5 {nested} 2. Nf3 (2. c3 d5)) 2.Nf3 $1 Nc6 *\n\
+                    \n\
+                    [FEN \"rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1\"]\n\
+                    1... e5 2. Nf3 ; rest of the line is a comment 2. Nc3\n\
+                    1-0\n";
+        let openings = parse_pgn(text).unwrap();
+        assert_eq!(openings.len(), 2);
+        assert_eq!(openings[0].fen, STARTING_FEN);
+        assert_eq!(openings[0].moves.iter().map(|mov| mov.to_string()).collect::<Vec<_>>(), ["e2e4", "e7e5", "g1f3", "b8c6"]);
+        assert_eq!(openings[1].fen, "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+        assert_eq!(openings[1].moves.iter().map(|mov| mov.to_string()).collect::<Vec<_>>(), ["e7e5", "g1f3"]);
+    }
+
+    #[test]
+    fn rejects_invalid_openings() {
+        assert!(parse_pgn("[FEN \"not a fen\"]\n1. e4 *\n").unwrap_err().starts_with("line 1: "));
+        assert!(parse_pgn("[Event \"?\"]\n1. e4 e5\n2. Ke3 *\n").unwrap_err().starts_with("line 3: illegal move Ke3"));
+        assert!(parse_epd("4k3/8/8/8/8/8/8/4K3 w - - bm Kd2;\n\n8/8/8 w - -\n").unwrap_err().starts_with("line 3: "));
+        let openings = parse_epd("4k3/8/8/8/8/8/8/4K3 w - - bm Kd2;\n").unwrap();
+        assert_eq!(openings[0].fen, "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+    }
+
+    #[test]
+    fn writes_pgn() {
+        let players = ["first".to_string(), "second".to_string()];
+        let time_control = TimeControl { time: 10000, increment: 100 };
+        let record = |fen: &str, moves: &[&str], result| {
+            let mut board = Board::from_fen(fen);
+            let moves = moves
+                .iter()
+                .map(|mov| {
+                    let mov = board.parse_uci_move(mov).unwrap();
+                    board.make_move(mov);
+                    mov
+                })
+                .collect();
+            GameRecord { fen: fen.to_string(), moves, result, termination: None }
+        };
+
+        let pgn = record(STARTING_FEN, &["e2e4", "e7e5", "g1f3"], GameResult::WhiteWin).pgn([&players[0], &players[1]], "1.1", &time_control);
+        assert!(pgn.contains("[Round \"1.1\"]\n[White \"first\"]\n[Black \"second\"]\n[Result \"1-0\"]\n"));
+        assert!(pgn.contains("[TimeControl \"10+0.1\"]\n"));
+        assert!(!pgn.contains("[FEN "));
+        assert!(pgn.ends_with("\n\n1. e4 e5 2. Nf3 1-0\n\n"));
+
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 12";
+        let mut record = record(fen, &["e7e5", "g1f3", "b8c6"], GameResult::Draw);
+        record.termination = Some("adjudication");
+        let pgn = record.pgn([&players[1], &players[0]], "2.2", &time_control);
+        assert!(pgn.contains(&format!("[FEN \"{}\"]\n[SetUp \"1\"]\n", fen)));
+        assert!(pgn.contains("[Termination \"adjudication\"]\n"));
+        assert!(pgn.ends_with("\n\n12... e5 13. Nf3 Nc6 1/2-1/2\n\n"));
+    }
+}
@@ -0,0 +1,86 @@
+use chess_engine::board::piece_move::Move;
+use chess_engine::board::{Board, STARTING_FEN};
+use chess_engine::evaluation::kpk;
+use chess_engine::render::{render_png, render_svg, RenderOptions};
+use chess_engine::search::listener::SilentListener;
+use chess_engine::search::{Search, SearchMode, SearchParams};
+
+const PV_ARROWS: usize = 4;
+
+// Draws a position as a diagram, a PNG or an SVG depending on the extension of the output.
+//
+// Usage: render <output> [--fen <fen>] [--moves <uci moves>...] [--pv <depth>] [--flip] [--no-coordinates]
+//
+// The last of the moves played from the position is highlighted. With --pv the position is
+// searched to the given depth and the first moves of the principal variation are drawn as arrows.
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut output_path = None;
+    let mut fen = STARTING_FEN.to_string();
+    let mut moves = Vec::new();
+    let mut pv_depth = None;
+    let mut options = RenderOptions::default();
+    let mut args_iter = args.iter().skip(1).peekable();
+    while let Some(arg) = args_iter.next() {
+        match arg.as_str() {
+            "--fen" => fen = args_iter.next().cloned().unwrap_or_else(|| exit_with_usage("--fen needs a value")),
+            "--moves" => {
+                while let Some(mov) = args_iter.next_if(|arg| !arg.starts_with("--")) {
+                    moves.push(mov.clone());
+                }
+            }
+            "--pv" => {
+                let depth = args_iter.next().unwrap_or_else(|| exit_with_usage("--pv needs a value"));
+                pv_depth = Some(depth.parse().unwrap_or_else(|_| exit_with_usage(&format!("invalid depth: {}", depth))));
+            }
+            "--flip" => options.flipped = true,
+            "--no-coordinates" => options.coordinates = false,
+            _ => output_path = Some(arg.clone()),
+        }
+    }
+    let Some(output_path) = output_path else {
+        exit_with_usage("expected an output file");
+    };
+
+    let mut board = Board::try_from_fen(&fen).unwrap_or_else(|error| exit_with_error(&error));
+    for move_string in moves.iter().flat_map(|moves| moves.split_whitespace()) {
+        let mov = match board.parse_uci_move(move_string) {
+            Ok(Move::NULL) => exit_with_error(&format!("null moves can't be drawn, --moves takes legal moves only: {}", move_string)),
+            Ok(mov) => mov,
+            Err(error) => exit_with_error(&error.to_string()),
+        };
+        board.make_move(mov);
+        options.last_move = Some(mov);
+    }
+
+    if let Some(depth) = pv_depth {
+        kpk::initialize();
+        let mut search = Search { listener: Box::new(SilentListener), ..Default::default() };
+        let search_params = SearchParams {
+            depth: Some(depth),
+            search_mode: SearchMode::Infinite,
+            use_book: false,
+            ..Default::default()
+        };
+        let result = search.search(search_params, &mut board.clone());
+        options = options.with_pv(&result, PV_ARROWS);
+    }
+
+    if output_path.ends_with(".svg") {
+        std::fs::write(&output_path, render_svg(&board, &options)).unwrap_or_else(|error| exit_with_error(&format!("{}: {}", output_path, error)));
+    } else {
+        render_png(&board, &options).save(&output_path).unwrap_or_else(|error| exit_with_error(&format!("{}: {}", output_path, error)));
+    }
+    println!("Diagram written to {}", output_path);
+}
+
+fn exit_with_error(error: &str) -> ! {
+    eprintln!("{}", error);
+    std::process::exit(1);
+}
+
+fn exit_with_usage(error: &str) -> ! {
+    eprintln!("{}", error);
+    eprintln!("usage: render <output> [--fen <fen>] [--moves <uci moves>...] [--pv <depth>] [--flip] [--no-coordinates]");
+    std::process::exit(1);
+}
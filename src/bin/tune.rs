@@ -0,0 +1,264 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::sync::Arc;
+use std::time::Instant;
+
+use chess_engine::board::piece::PieceType;
+use chess_engine::board::Board;
+use chess_engine::evaluation::endgame::{find_endgame, scale_factor, SCALE_NORMAL};
+use chess_engine::evaluation::eval_params::EvalParams;
+use chess_engine::evaluation::score::MAX_PHASE;
+use chess_engine::evaluation::{evaluate, phase};
+use chess_engine::search::listener::SilentListener;
+use chess_engine::search::Search;
+
+const DEFAULT_EPOCHS: usize = 2000;
+const LEARNING_RATE: f64 = 1.0;
+const BETA1: f64 = 0.9;
+const BETA2: f64 = 0.999;
+const EPSILON: f64 = 1e-8;
+const REPORT_INTERVAL: usize = 50;
+
+// Texel tuning of the material values and piece-square tables against game results.
+//
+// Usage: tune <positions> <output> [epochs] [--params <file>]
+//
+// Every line of the positions file holds a FEN and the result from white's point of view, in any of
+// the forms `<fen> [1.0]`, `<fen> c9 "1/2-1/2";`, `<fen> | <eval> | 0.0` or `<fen> 1-0`.
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let mut positional = Vec::new();
+    let mut params = EvalParams::default();
+    let mut args_iter = args.iter().skip(1);
+    while let Some(arg) = args_iter.next() {
+        if arg == "--params" {
+            let Some(path) = args_iter.next() else {
+                exit_with_usage("--params needs a file");
+            };
+            params = EvalParams::load(path).unwrap_or_else(|error| exit_with_error(&error));
+        } else {
+            positional.push(arg.as_str());
+        }
+    }
+    let [positions_path, output_path, rest @ ..] = positional.as_slice() else {
+        exit_with_usage("expected a positions and an output file");
+    };
+    let epochs = rest.first().map_or(DEFAULT_EPOCHS, |epochs| epochs.parse().unwrap_or_else(|_| exit_with_usage(&format!("invalid number of epochs: {}", epochs))));
+
+    let start = Instant::now();
+    let tuner = Tuner::new(&params, positions_path).unwrap_or_else(|error| exit_with_error(&error));
+    println!("{} quiet positions loaded in {:.1}s", tuner.positions.len(), start.elapsed().as_secs_f32());
+    if tuner.positions.is_empty() {
+        return;
+    }
+
+    let weights = tuner.tune(params.to_vector().iter().map(|&weight| weight as f64).collect(), epochs);
+    params.set_vector(&weights.iter().map(|weight| weight.round() as i32).collect::<Vec<i32>>());
+    params.save(output_path).unwrap_or_else(|error| exit_with_error(&error));
+    println!("Parameters written to {}", output_path);
+}
+
+fn exit_with_error(error: &str) -> ! {
+    eprintln!("{}", error);
+    std::process::exit(1);
+}
+
+fn exit_with_usage(error: &str) -> ! {
+    eprintln!("{}", error);
+    eprintln!("usage: tune <positions> <output> [epochs] [--params <file>]");
+    std::process::exit(1);
+}
+
+struct Position {
+    result: f64,
+    // Evaluation from white's point of view of every term that isn't tuned
+    fixed_eval: f64,
+    // Weight index and the coefficient it is multiplied with in the evaluation from white's point of view
+    coefficients: Vec<(usize, f64)>,
+}
+
+struct Tuner {
+    positions: Vec<Position>,
+    tuned: Vec<bool>, // Which weights of the parameter vector are optimized
+    k: f64,
+}
+
+impl Tuner {
+    fn new(params: &EvalParams, path: &str) -> Result<Self, String> {
+        let offsets = field_offsets(params);
+        let piece_values = offsets("piece_values");
+        let midgame_pst = offsets("midgame_pst");
+        let endgame_pst = offsets("endgame_pst");
+
+        let mut tuned = vec![false; params.to_vector().len()];
+        // The king value never changes the balance, as both sides always have one
+        tuned[piece_values..piece_values + PieceType::King as usize].fill(true);
+        tuned[midgame_pst..midgame_pst + 6 * 64].fill(true);
+        tuned[endgame_pst..endgame_pst + 6 * 64].fill(true);
+
+        let weights = params.to_vector();
+        let params = Arc::new(params.clone());
+        let mut search = Search { listener: Box::new(SilentListener), ..Default::default() };
+
+        let file = File::open(path).map_err(|error| format!("could not open {}: {}", path, error))?;
+        let mut positions = Vec::new();
+        let mut skipped = 0; // Lines without a valid position and result
+        for (line_number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line.map_err(|error| format!("could not read {}: {}", path, error))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let Some((mut board, result)) = parse_line(&line).and_then(|(fen, result)| Some((Board::try_from_fen(&fen).ok()?, result))) else {
+                eprintln!("skipping line {}: {}", line_number + 1, line);
+                skipped += 1;
+                continue;
+            };
+            board.set_eval_params(params.clone());
+
+            // Only quiet positions, where the static evaluation is what the search would return
+            let eval = evaluate(&board);
+            if board.in_check() || search.quiescence(&mut board) != eval {
+                continue;
+            }
+            // Specialized and scaled endgames aren't linear in the weights
+            if find_endgame(&board).is_some() || scale_factor(&board, eval) != SCALE_NORMAL {
+                continue;
+            }
+
+            let phase = phase(&board) as f64 / MAX_PHASE as f64;
+            let mut coefficients = Vec::new();
+            let mut tuned_eval = 0.0;
+            for square in 0..64 {
+                let Some(piece) = board.squares[square] else { continue };
+                let piece_type = piece.piece_type() as usize;
+                let sign = piece.side().factor() as f64;
+                let relative_square = if sign > 0.0 { square } else { square ^ 56 };
+                coefficients.push((midgame_pst + piece_type * 64 + relative_square, sign * phase));
+                coefficients.push((endgame_pst + piece_type * 64 + relative_square, sign * (1.0 - phase)));
+                if piece.piece_type() != PieceType::King {
+                    coefficients.push((piece_values + piece_type, sign));
+                }
+            }
+            for &(index, coefficient) in coefficients.iter() {
+                tuned_eval += weights[index] as f64 * coefficient;
+            }
+            let white_eval = (eval * board.side.factor()) as f64;
+            positions.push(Position { result, fixed_eval: white_eval - tuned_eval, coefficients });
+        }
+
+        if skipped > 0 {
+            eprintln!("{} malformed lines skipped", skipped);
+        }
+        Ok(Self { positions, tuned, k: 1.0 })
+    }
+
+    fn evaluate(&self, position: &Position, weights: &[f64]) -> f64 {
+        position.fixed_eval + position.coefficients.iter().map(|&(index, coefficient)| weights[index] * coefficient).sum::<f64>()
+    }
+    fn error(&self, weights: &[f64], k: f64) -> f64 {
+        self.positions.iter().map(|position| (position.result - sigmoid(self.evaluate(position, weights), k)).powi(2)).sum::<f64>() / self.positions.len() as f64
+    }
+
+    // Scaling of the sigmoid that best maps the current evaluation to the results
+    fn fit_k(&self, weights: &[f64]) -> f64 {
+        let (mut low, mut high) = (0.0, 10.0);
+        for _ in 0..100 {
+            let first = low + (high - low) / 3.0;
+            let second = high - (high - low) / 3.0;
+            if self.error(weights, first) < self.error(weights, second) {
+                high = second;
+            } else {
+                low = first;
+            }
+        }
+        (low + high) / 2.0
+    }
+
+    fn gradient(&self, weights: &[f64]) -> Vec<f64> {
+        let mut gradient = vec![0.0; weights.len()];
+        for position in self.positions.iter() {
+            let prediction = sigmoid(self.evaluate(position, weights), self.k);
+            // Derivative of the squared error with respect to the evaluation
+            let slope = 2.0 * (prediction - position.result) * prediction * (1.0 - prediction) * self.k / 400.0;
+            for &(index, coefficient) in position.coefficients.iter() {
+                gradient[index] += slope * coefficient;
+            }
+        }
+        let scale = 1.0 / self.positions.len() as f64;
+        gradient.iter_mut().for_each(|value| *value *= scale);
+        gradient
+    }
+
+    // Adam on the full batch
+    fn tune(mut self, mut weights: Vec<f64>, epochs: usize) -> Vec<f64> {
+        self.k = self.fit_k(&weights);
+        println!("K = {:.4}, initial error {:.6}", self.k, self.error(&weights, self.k));
+
+        let mut momentum = vec![0.0; weights.len()];
+        let mut velocity = vec![0.0; weights.len()];
+        for epoch in 1..=epochs {
+            let gradient = self.gradient(&weights);
+            for index in 0..weights.len() {
+                if !self.tuned[index] {
+                    continue;
+                }
+                momentum[index] = BETA1 * momentum[index] + (1.0 - BETA1) * gradient[index];
+                velocity[index] = BETA2 * velocity[index] + (1.0 - BETA2) * gradient[index] * gradient[index];
+                let momentum_corrected = momentum[index] / (1.0 - BETA1.powi(epoch as i32));
+                let velocity_corrected = velocity[index] / (1.0 - BETA2.powi(epoch as i32));
+                weights[index] -= LEARNING_RATE * momentum_corrected / (velocity_corrected.sqrt() + EPSILON);
+            }
+            if epoch % REPORT_INTERVAL == 0 || epoch == epochs {
+                println!("epoch {} error {:.6}", epoch, self.error(&weights, self.k));
+            }
+        }
+        weights
+    }
+}
+
+fn sigmoid(eval: f64, k: f64) -> f64 {
+    1.0 / (1.0 + (-k * eval / 400.0).exp())
+}
+
+// Returns a lookup from parameter name to the index of its first weight in the parameter vector
+fn field_offsets(params: &EvalParams) -> impl Fn(&str) -> usize {
+    let mut offsets = Vec::new();
+    let mut offset = 0;
+    for (name, weights) in params.fields() {
+        offsets.push((name, offset));
+        offset += weights.count();
+    }
+    move |name| offsets.iter().find(|(field, _)| *field == name).unwrap().1
+}
+
+fn parse_line(line: &str) -> Option<(String, f64)> {
+    let line = line.trim().trim_end_matches(';');
+    let (fen, result) = if line.contains('|') {
+        let fields: Vec<&str> = line.split('|').collect();
+        (fields[0], *fields.last().unwrap())
+    } else if let Some((fen, rest)) = line.split_once('[') {
+        (fen, rest.trim_end_matches(']'))
+    } else if let Some((fen, rest)) = line.split_once(" c9 ") {
+        (fen, rest.trim_matches('"'))
+    } else {
+        line.rsplit_once(' ')?
+    };
+
+    let result = match result.trim().trim_matches('"') {
+        "1-0" => 1.0,
+        "0-1" => 0.0,
+        "1/2-1/2" => 0.5,
+        result => result.parse().ok()?,
+    };
+
+    // EPD lines leave out the move counters
+    let mut fen = fen.trim().to_string();
+    let field_count = fen.split_whitespace().count();
+    if field_count < 4 {
+        return None;
+    }
+    if field_count == 4 {
+        fen.push_str(" 0 1");
+    }
+    Some((fen, result))
+}
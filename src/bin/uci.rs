@@ -1,48 +1,49 @@
 use std::io::{self, BufRead};
-use std::option::Option;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc;
-use std::sync::Arc;
-use std::thread::{self, spawn, JoinHandle};
 use std::time::Instant;
 
 use chess_engine::bench;
-use chess_engine::board::zobrist_hash::initialize_zobrist_tables;
-use chess_engine::board::{Board, Side};
-use chess_engine::move_generation::attack_tables::{get_between_ray, get_checkmask_between, initialize_tables};
+use chess_engine::board::bitboard::Bitboard;
+use chess_engine::board::piece::{Piece, PieceType};
+use chess_engine::board::piece_move::Move;
+use chess_engine::board::san::square_name;
+use chess_engine::board::{Side, STARTING_FEN};
+use chess_engine::engine::Engine;
+use chess_engine::evaluation::trace::evaluate_trace;
 use chess_engine::move_generation::generate_moves;
-use chess_engine::perft::perft;
-use chess_engine::search::{Search, SearchMode, SearchParams, SearchResult};
+use chess_engine::perft::{divide, perft};
+use chess_engine::search::listener::UciListener;
+use chess_engine::search::transposition_table::MAX_HASH_MB;
+use chess_engine::search::{SearchMode, SearchParams};
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
     if let Some(arg) = args.get(1) {
+        // bench [depth] [threads] [hash] [quiet], where threads must be 1 as the search is single
+        // threaded. It is only accepted because OpenBench passes it.
         if arg == "bench" {
-            bench::bench();
+            let quiet = args.iter().any(|arg| arg == "quiet");
+            let numbers: Result<Vec<usize>, _> = args[2..].iter().filter(|&arg| arg != "quiet").map(|arg| arg.parse()).collect();
+            let numbers = match numbers {
+                Ok(numbers) if numbers.len() <= 3 && numbers.get(1).is_none_or(|&threads| threads == 1) => numbers,
+                _ => {
+                    eprintln!("usage: uci bench [depth] [threads, only 1] [hash in MB] [quiet]");
+                    std::process::exit(1);
+                }
+            };
+            let number = |index: usize, default: usize| numbers.get(index).copied().unwrap_or(default);
+            bench::bench(number(0, bench::BENCHMARK_DEPTH as usize) as u32, number(2, bench::BENCHMARK_HASH_MB), quiet);
         }
         return;
     }
     Uci::start();
 }
 
-fn spawn_stdin_channel() -> mpsc::Receiver<String> {
-    let (tx, rx) = mpsc::channel::<String>();
-    thread::spawn(move || loop {
-        let mut buffer = String::new();
-        io::stdin().read_line(&mut buffer).unwrap();
-        tx.send(buffer).unwrap();
-    });
-    rx
-}
-
 pub struct Uci {
     name: String,
     author: String,
-    board: Board,
+    engine: Engine,
     is_debug: bool,
     is_running: bool,
-    should_quit_search: Arc<AtomicBool>,
-    search_thread: Option<JoinHandle<SearchResult>>,
 }
 
 impl Uci {
@@ -50,77 +51,61 @@ impl Uci {
         let mut uci = Self {
             name: "".to_string(),
             author: "".to_string(),
-            board: Board::start_pos(),
+            engine: Engine::new(),
             is_debug: false,
             is_running: true,
-            should_quit_search: Arc::new(AtomicBool::new(false)),
-            search_thread: None,
         };
+        uci.engine.set_listener(Box::new(UciListener::default()));
 
-        let stdin_channel = spawn_stdin_channel();
-
-        while uci.is_running {
-            match stdin_channel.try_recv() {
-                Ok(line) => {
-                    uci.parse_command(line);
-                }
-                Err(mpsc::TryRecvError::Empty) => {}
-                Err(mpsc::TryRecvError::Disconnected) => panic!("Channel disconnected"),
-            }
-
-            // Check if search has finished
-            uci.check_search_result();
-
-            // Small sleep to prevent CPU spinning
-            thread::sleep(std::time::Duration::from_millis(1));
-        }
-    }
-
-    fn check_search_result(&mut self) {
-        if let Some(handle) = self.search_thread.take() {
-            if handle.is_finished() {
-                match handle.join() {
-                    Ok(result) => {
-                        if let Some(mov) = result.pv.first() {
-                            println!("bestmove {}", mov);
-                        } else {
-                            println!("bestmove (none)");
-                        }
-                    }
-                    Err(e) => eprintln!("Search thread panicked: {:?}", e),
-                }
-            } else {
-                // Put the handle back if search isn't finished
-                self.search_thread = Some(handle);
+        // Searches run on their own thread, so commands like stop and isready are answered while
+        // searching. The loop ends on quit or when the GUI closes stdin.
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            uci.parse_command(&line);
+            if !uci.is_running {
+                break;
             }
         }
+        uci.engine.stop();
+        uci.engine.wait();
     }
 
-    fn parse_command(&mut self, full_command: String) {
+    fn parse_command(&mut self, full_command: &str) {
         let command = full_command.split_whitespace().next().unwrap_or("");
         match command {
+            "" => {}
             "uci" => self.identify(),
             "debug" => self.set_debug(full_command),
             "isready" => self.synchronize(),
+            "setoption" => self.set_option(full_command),
+            "ucinewgame" => self.engine.new_game(),
             "position" => self.set_position(full_command),
             "go" => self.go(full_command),
             "fen" => {
-                println!("{}", self.board.fen());
-            }
-            "stop" => {
-                self.should_quit_search.store(true, Ordering::SeqCst);
+                println!("{}", self.engine.board().fen());
             }
-            "quit" => {
-                self.should_quit_search.store(true, Ordering::SeqCst);
-                self.quit();
-            }
-            _ => {}
+            "eval" => print!("{}", evaluate_trace(&self.engine.evaluation_board())),
+            // Commands for debugging positions by hand
+            "d" => self.display(),
+            "perft" => self.divide(full_command),
+            "moves" => self.list_moves(),
+            "flip" => self.flip(),
+            "see" => self.see(full_command),
+            "stop" => self.engine.stop(),
+            "quit" => self.quit(),
+            _ => println!("info string unknown command: {}", command),
         }
     }
 
     fn identify(&self) {
         println!("id name chess_engine");
         println!("id name vilhelm lindell");
+        let options = self.engine.options();
+        println!("option name OwnBook type check default {}", options.own_book);
+        println!("option name Move Overhead type spin default {} min 0 max 5000", options.move_overhead);
+        println!("option name Hash type spin default {} min 1 max {}", options.hash, MAX_HASH_MB);
+        println!("option name EvalFile type string default <empty>");
+        println!("option name EvalParams type string default <empty>");
         println!("uciok");
     }
     //fn register(&self, command: String) {
@@ -133,56 +118,47 @@ impl Uci {
     //    }
     //}
     //}
-    fn set_debug(&mut self, command: String) {
+    // Takes effect from the next search, as the listener can't be swapped during one
+    fn set_debug(&mut self, command: &str) {
         if command.ends_with("on") {
             self.is_debug = true;
         } else if command.ends_with("off") {
             self.is_debug = false;
         }
     }
+    fn set_option(&mut self, command: &str) {
+        // setoption name <id> [value <x>], where the name itself may contain spaces
+        let command = command.trim();
+        let Some(rest) = command.strip_prefix("setoption name ") else {
+            return;
+        };
+        let (name, value) = rest.split_once(" value ").unwrap_or((rest, ""));
+        if let Err(message) = self.engine.set_option(name.trim(), value.trim()) {
+            println!("info string {}", message);
+        }
+    }
     fn synchronize(&self) {
         println!("readyok");
     }
-    fn set_position(&mut self, command: String) {
-        let words: Vec<&str> = command.split_whitespace().collect();
-        let mut moves_index = 8;
-        let mut board = {
-            if words[1] == "startpos" {
-                moves_index = 2;
-                Board::start_pos()
-            } else if words[1] == "fen" {
-                let fen = words[2..8].join(" ");
-                Board::from_fen(&fen)
-            } else {
+    fn set_position(&mut self, command: &str) {
+        // position (startpos | fen <fen>) [moves <move>...]
+        let words: Vec<&str> = command.split_whitespace().skip(1).collect();
+        let moves_index = words.iter().position(|&word| word == "moves").unwrap_or(words.len());
+        let moves = words.get(moves_index + 1..).unwrap_or(&[]);
+        let fen = match &words[..moves_index] {
+            ["startpos"] => STARTING_FEN.to_string(),
+            ["fen", fields @ ..] => fields.join(" "),
+            _ => {
+                println!("info string invalid position command: {}", command);
                 return;
             }
         };
-        if let Some(move_string) = words.get(moves_index) {
-            if move_string != &"moves" {
-                return;
-            }
-            let moves = &words[(moves_index + 1)..];
-            for mov in moves {
-                let all_moves = generate_moves(&board);
-                all_moves.iter().enumerate().for_each(|(i, val)| {
-                    if val.to_string() == *mov.to_string() {
-                        board.make_move(all_moves[i]);
-                        println!("{} {}", mov.to_string(), board.fen());
-                        //println!("{}: {}", val.to_string(), self.board.evaluate());
-                    }
-                })
-            }
+        if let Err(message) = self.engine.set_position(&fen, moves) {
+            println!("info string {}", message);
         }
-        self.board = board;
     }
 
-    fn go(&mut self, command: String) {
-        // Stop any existing search
-        if let Some(handle) = self.search_thread.take() {
-            self.should_quit_search.store(true, Ordering::SeqCst);
-            let _ = handle.join();
-        }
-
+    fn go(&mut self, command: &str) {
         let mut words = command.split_whitespace().peekable();
         words.next();
         let mut search_params = SearchParams::default();
@@ -191,8 +167,12 @@ impl Uci {
         while let Some(token) = words.next() {
             match token {
                 "perft" => {
+                    let Ok(depth) = words.next().unwrap_or("1").parse() else {
+                        println!("info string invalid perft depth");
+                        return;
+                    };
                     let start = Instant::now();
-                    let result = perft(&self.board.fen(), words.next().unwrap_or("1").parse().unwrap());
+                    let result = perft(&self.engine.board().fen(), depth);
                     let seconds = start.elapsed().as_secs_f32();
                     println!("Nodes: {}", result.nodes);
                     println!("Time elapsed: {}", seconds);
@@ -233,6 +213,14 @@ impl Uci {
                         }
                     }
                 }
+                "movestogo" => {
+                    if let Some(&moves_str) = words.peek() {
+                        if let Ok(moves_to_go) = moves_str.parse() {
+                            search_params.clock.moves_to_go = Some(moves_to_go);
+                            words.next();
+                        }
+                    }
+                }
                 "movetime" => {
                     if let Some(&time_str) = words.peek() {
                         if let Ok(move_time) = time_str.parse() {
@@ -242,39 +230,85 @@ impl Uci {
                         }
                     }
                 }
+                "depth" => {
+                    if let Some(&depth_str) = words.peek() {
+                        if let Ok(depth) = depth_str.parse() {
+                            search_params.depth = Some(depth);
+                            words.next();
+                        }
+                    }
+                }
+                "nodes" => {
+                    if let Some(&nodes_str) = words.peek() {
+                        if let Ok(nodes) = nodes_str.parse() {
+                            search_params.nodes = Some(nodes);
+                            words.next();
+                        }
+                    }
+                }
                 "infinite" => search_params.search_mode = SearchMode::Infinite,
                 "nobook" => search_params.use_book = false,
                 _ => {}
             }
         }
 
-        let mut search = Search::default();
-        let mut board_clone = self.board.clone();
-        search.should_quit = self.should_quit_search.clone();
-
-        self.search_thread = Some(thread::spawn(move || {
-            let result = search.search(search_params, &mut board_clone);
-            result
-        }));
-
-        //let mut search = Search::default();
-
-        //// Time the cloning of the board
-        ////let clone_start = Instant::now();
-        //let mut board_clone = self.board.clone();
-        ////let clone_duration = clone_start.elapsed();
-        ////println!("Cloning the board took: {:?}", clone_duration);
-
-        //search.should_quit = self.should_quit_search.clone();
+        self.engine.stop();
+        self.engine.wait();
+        self.engine.set_listener(Box::new(UciListener { debug: self.is_debug }));
+        self.engine.go(search_params);
+    }
 
-        //// Time the search itself inside the thread
-        //self.search_thread = Some(thread::spawn(move || {
-        //    //let search_start = Instant::now();
-        //    let result = search.search(search_params, &mut board_clone);
-        //    //let search_duration = search_start.elapsed();
-        //    //println!("Search took: {:?}", search_duration);
-        //    result
-        //}));
+    fn display(&self) {
+        let board = self.engine.board();
+        let squares = |bitboard: Bitboard| bitboard.map(square_name).collect::<Vec<String>>().join(" ");
+        let king = board.piece_squares[Piece::new(PieceType::King, board.side)].lsb();
+        println!("{}", board);
+        println!("Fen: {}", board.fen());
+        println!("Key: {:016x}", board.zobrist_hash);
+        println!("Checkers: {}", squares(board.attackers(king, board.side)));
+        println!("Pinned: {}", squares(board.absolute_pinned_squares));
+    }
+    fn divide(&self, command: &str) {
+        let Some(depth) = command.split_whitespace().nth(1).and_then(|depth| depth.parse().ok()).filter(|&depth| depth > 0) else {
+            println!("info string usage: perft <depth>");
+            return;
+        };
+        let start = Instant::now();
+        let counts = divide(&mut self.engine.board(), depth);
+        for (mov, nodes) in counts.iter() {
+            println!("{}: {}", mov, nodes);
+        }
+        let nodes: u64 = counts.iter().map(|(_, nodes)| nodes).sum();
+        println!();
+        println!("Nodes searched: {}", nodes);
+        println!("Time: {} ms", start.elapsed().as_millis());
+    }
+    fn list_moves(&self) {
+        let mut board = self.engine.board();
+        let mut moves: Vec<(String, String)> = generate_moves(&board).into_iter().map(|mov| (mov.to_string(), board.san(mov))).collect();
+        moves.sort();
+        for (uci, san) in moves.iter() {
+            println!("{} {}", uci, san);
+        }
+        println!("Legal moves: {}", moves.len());
+    }
+    fn flip(&self) {
+        let board = self.engine.board().mirrored();
+        if let Err(message) = self.engine.set_position(&board.fen(), &[]) {
+            println!("info string {}", message);
+        }
+    }
+    // Exchange value of a move in UCI or SAN
+    fn see(&self, command: &str) {
+        let board = self.engine.board();
+        let Some(move_string) = command.split_whitespace().nth(1) else {
+            println!("info string usage: see <move>");
+            return;
+        };
+        match board.parse_uci_move(move_string).ok().filter(|&mov| mov != Move::NULL).or_else(|| board.parse_san(move_string)) {
+            Some(mov) => println!("See: {}", board.see(mov)),
+            None => println!("info string illegal move: {}", move_string),
+        }
     }
 
     fn ponder(&self) {}
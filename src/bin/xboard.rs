@@ -0,0 +1,289 @@
+use std::io::{self, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+
+use chess_engine::board::piece_move::Move;
+use chess_engine::board::{Side, STARTING_FEN};
+use chess_engine::engine::Engine;
+use chess_engine::search::listener::{SearchInfo, SearchListener, XboardListener};
+use chess_engine::search::{SearchMode, SearchParams, SearchResult};
+
+fn main() {
+    Xboard::start();
+}
+
+// Plays the best move as soon as the search finishes, from the search thread, so the front end can
+// block on stdin. The move is sent back before it is printed, so it is recorded before the GUI
+// answers it.
+struct PlayingListener {
+    output: XboardListener,
+    played: mpsc::Sender<Move>,
+    canceled: Arc<AtomicBool>, // Set when the search is stopped without playing its move
+}
+
+impl SearchListener for PlayingListener {
+    fn on_iteration_complete(&mut self, info: &SearchInfo) {
+        self.output.on_iteration_complete(info);
+    }
+    fn on_search_finished(&mut self, result: &SearchResult) {
+        if self.canceled.load(Ordering::SeqCst) {
+            return;
+        }
+        if let Some(&mov) = result.pv.first() {
+            self.played.send(mov).unwrap();
+            println!("move {}", mov);
+        }
+    }
+}
+
+// Time control set with `level`, in milliseconds
+#[derive(Default)]
+struct Level {
+    moves_per_session: usize, // 0 for the whole game
+    base: u128,
+    inc: u128,
+}
+
+/// WinBoard/CECP version 2 front end to the same engine as the UCI binary. The game is kept as the
+/// starting position and the moves played since, so moves can be taken back.
+pub struct Xboard {
+    engine: Engine,
+    start_fen: String,
+    moves: Vec<String>,
+    engine_side: Option<Side>, // None in force mode
+    analyzing: bool,
+    post: bool,
+    level: Level,
+    move_time: Option<u128>, // Fixed time per move set with `st`
+    max_depth: Option<u32>,
+    time: u128,      // Engine's clock in milliseconds
+    opponent_time: u128,
+    thinking: Option<Arc<AtomicBool>>, // Cancel flag of the search for a move the engine will play
+    played_sender: mpsc::Sender<Move>,
+    played: mpsc::Receiver<Move>, // Moves played by the engine and not yet recorded
+    is_running: bool,
+}
+
+impl Xboard {
+    pub fn start() {
+        let (played_sender, played) = mpsc::channel();
+        let mut xboard = Self {
+            engine: Engine::new(),
+            start_fen: STARTING_FEN.to_string(),
+            moves: Vec::new(),
+            engine_side: Some(Side::Black),
+            analyzing: false,
+            post: false,
+            level: Level::default(),
+            move_time: None,
+            max_depth: None,
+            time: 0,
+            opponent_time: 0,
+            thinking: None,
+            played_sender,
+            played,
+            is_running: true,
+        };
+
+        // Searches run on their own thread and play their move from the listener, so the loop only
+        // wakes up for commands. It ends on quit or when the GUI closes stdin.
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            xboard.record_played_moves();
+            xboard.parse_command(line.trim());
+            if !xboard.is_running {
+                break;
+            }
+        }
+        xboard.cancel();
+    }
+
+    fn parse_command(&mut self, command: &str) {
+        let (name, arguments) = command.split_once(' ').unwrap_or((command, ""));
+        match name {
+            "xboard" | "accepted" | "rejected" | "random" | "hard" | "easy" | "computer" | "name" | "rating" | "ics" | "." | "" => {}
+            "protover" => self.identify(),
+            "ping" => println!("pong {}", arguments),
+            "new" => {
+                self.cancel();
+                self.engine.new_game();
+                self.start_fen = STARTING_FEN.to_string();
+                self.moves.clear();
+                self.engine_side = Some(Side::Black);
+                self.move_time = None;
+                self.max_depth = None;
+                self.restart_analysis();
+            }
+            "setboard" => {
+                self.cancel();
+                if self.engine.set_position(arguments, &[]).is_err() {
+                    println!("tellusererror Illegal position");
+                    return;
+                }
+                self.start_fen = arguments.to_string();
+                self.moves.clear();
+                self.restart_analysis();
+            }
+            "usermove" => self.user_move(arguments),
+            "go" => {
+                self.cancel();
+                self.engine_side = Some(self.engine.board().side);
+                self.think();
+            }
+            "force" | "result" => {
+                self.cancel();
+                self.engine_side = None;
+            }
+            "playother" => {
+                self.cancel();
+                self.engine_side = Some(self.engine.board().side.enemy());
+            }
+            "?" => self.engine.stop(),
+            "level" => self.set_level(arguments),
+            "st" => self.move_time = arguments.parse::<u128>().ok().map(|seconds| seconds * 1000),
+            "sd" => self.max_depth = arguments.parse().ok(),
+            "time" => self.time = arguments.parse::<u128>().unwrap_or(0) * 10,
+            "otim" => self.opponent_time = arguments.parse::<u128>().unwrap_or(0) * 10,
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "analyze" => {
+                self.cancel();
+                self.engine_side = None;
+                self.analyzing = true;
+                self.restart_analysis();
+            }
+            "exit" => {
+                self.engine.stop();
+                self.engine.wait();
+                self.analyzing = false;
+            }
+            "undo" => self.take_back(1),
+            "remove" => self.take_back(2),
+            "quit" => {
+                self.cancel();
+                self.is_running = false;
+            }
+            // Moves without the usermove prefix from GUIs that ignore the feature
+            _ if self.is_legal(name) => self.user_move(name),
+            _ => println!("Error (unknown command): {}", name),
+        }
+    }
+
+    fn identify(&self) {
+        println!("feature done=0");
+        println!("feature myname=\"chess_engine\" ping=1 setboard=1 usermove=1 playother=1 analyze=1 colors=0 san=0 sigint=0 sigterm=0 reuse=1");
+        println!("feature done=1");
+    }
+
+    fn is_legal(&self, mov: &str) -> bool {
+        self.engine.board().parse_uci_move(mov).is_ok()
+    }
+
+    fn set_position(&self) {
+        let moves: Vec<&str> = self.moves.iter().map(String::as_str).collect();
+        self.engine.set_position(&self.start_fen, &moves).expect("game moves are legal");
+    }
+
+    fn user_move(&mut self, mov: &str) {
+        self.cancel();
+        self.moves.push(mov.to_string());
+        let moves: Vec<&str> = self.moves.iter().map(String::as_str).collect();
+        if self.engine.set_position(&self.start_fen, &moves).is_err() {
+            self.moves.pop();
+            println!("Illegal move: {}", mov);
+            return;
+        }
+        if self.analyzing {
+            self.restart_analysis();
+        } else if self.engine_side == Some(self.engine.board().side) {
+            self.think();
+        }
+    }
+
+    fn take_back(&mut self, plies: usize) {
+        self.cancel();
+        if plies > self.moves.len() {
+            println!("Error (no moves to take back): {}", if plies == 1 { "undo" } else { "remove" });
+            return;
+        }
+        self.moves.truncate(self.moves.len() - plies);
+        self.set_position();
+        self.restart_analysis();
+    }
+
+    fn set_level(&mut self, arguments: &str) {
+        // level <moves per session> <minutes[:seconds]> <increment seconds>
+        let words: Vec<&str> = arguments.split_whitespace().collect();
+        let [moves, base, inc] = words[..] else {
+            println!("Error (bad level): {}", arguments);
+            return;
+        };
+        let (minutes, seconds) = base.split_once(':').unwrap_or((base, "0"));
+        let (Ok(moves), Ok(minutes), Ok(seconds), Ok(inc)) = (moves.parse(), minutes.parse::<u128>(), seconds.parse::<u128>(), inc.parse::<f64>()) else {
+            println!("Error (bad level): {}", arguments);
+            return;
+        };
+        self.level = Level { moves_per_session: moves, base: minutes * 60000 + seconds * 1000, inc: (inc * 1000.0) as u128 };
+        self.move_time = None;
+        self.time = self.level.base;
+        self.opponent_time = self.level.base;
+    }
+
+    // Stops the search without playing its move, unless it was already played
+    fn cancel(&mut self) {
+        if let Some(canceled) = self.thinking.take() {
+            canceled.store(true, Ordering::SeqCst);
+        }
+        self.engine.stop();
+        self.engine.wait();
+        self.record_played_moves();
+    }
+
+    fn record_played_moves(&mut self) {
+        while let Ok(mov) = self.played.try_recv() {
+            self.thinking = None;
+            self.moves.push(mov.to_string());
+            self.set_position();
+        }
+    }
+
+    fn start_search(&mut self, params: SearchParams, listener: Box<dyn SearchListener>) {
+        self.engine.set_listener(listener);
+        self.engine.go(params);
+    }
+
+    fn think(&mut self) {
+        let board = self.engine.board();
+        let mut params = SearchParams { depth: self.max_depth, use_book: true, ..Default::default() };
+        if let Some(move_time) = self.move_time {
+            params.search_mode = SearchMode::MoveTime;
+            params.move_time = move_time;
+        } else {
+            params.search_mode = SearchMode::Clock;
+            params.clock.time[board.side] = self.time;
+            params.clock.time[board.side.enemy()] = self.opponent_time;
+            params.clock.inc = [self.level.inc; 2];
+            if self.level.moves_per_session > 0 {
+                let fullmove: usize = board.fen().split_whitespace().last().and_then(|number| number.parse().ok()).unwrap_or(1);
+                params.clock.moves_to_go = Some(self.level.moves_per_session - (fullmove - 1) % self.level.moves_per_session);
+            }
+        }
+        let canceled = Arc::new(AtomicBool::new(false));
+        let listener = PlayingListener {
+            output: XboardListener { post: self.post || self.analyzing },
+            played: self.played_sender.clone(),
+            canceled: canceled.clone(),
+        };
+        self.start_search(params, Box::new(listener));
+        self.thinking = Some(canceled);
+    }
+
+    fn restart_analysis(&mut self) {
+        if !self.analyzing {
+            return;
+        }
+        self.cancel();
+        let params = SearchParams { depth: self.max_depth, search_mode: SearchMode::Infinite, ..Default::default() };
+        self.start_search(params, Box::new(XboardListener { post: true }));
+    }
+}
@@ -6,37 +6,37 @@ use std::ops::Deref;
 const NOT_A_FILE: u64 = 0xfefefefefefefefe;
 const NOT_H_FILE: u64 = 0x7f7f7f7f7f7f7f7f;
 
-#[derive(MulAssign, ShrAssign, ShlAssign, BitOrAssign, BitAndAssign, BitXorAssign, BitAnd, BitOr, BitXor, Shr, Shl, Not, Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(MulAssign, ShrAssign, ShlAssign, BitOrAssign, BitAndAssign, BitXorAssign, BitAnd, BitOr, BitXor, Shr, Shl, Not, Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct Bitboard(pub u64);
 
 impl Bitboard {
     #[inline(always)]
-    pub fn from_square(square: usize) -> Self {
+    pub const fn from_square(square: usize) -> Self {
         Self(1 << square)
     }
 
     #[inline(always)]
-    pub fn bit(self, n: usize) -> u64 {
+    pub const fn bit(self, n: usize) -> u64 {
         (self.0 >> n) & 1
     }
     #[inline(always)]
-    pub fn set_bit(&mut self, n: usize) {
+    pub const fn set_bit(&mut self, n: usize) {
         self.0 |= 1 << n;
     }
     #[inline(always)]
-    pub fn clear_bit(&mut self, n: usize) {
+    pub const fn clear_bit(&mut self, n: usize) {
         self.0 &= !(1 << n);
     }
     #[inline(always)]
-    pub fn lsb(self) -> usize {
-        self.trailing_zeros() as usize
+    pub const fn lsb(self) -> usize {
+        self.0.trailing_zeros() as usize
     }
     #[inline(always)]
-    pub fn msb(self) -> usize {
-        63 - self.leading_zeros() as usize
+    pub const fn msb(self) -> usize {
+        63 - self.0.leading_zeros() as usize
     }
     #[inline(always)]
-    pub fn pop_lsb(&mut self) -> usize {
+    pub const fn pop_lsb(&mut self) -> usize {
         let index = self.lsb();
         self.clear_bit(index);
         index
@@ -3,8 +3,10 @@ use super::direction::Direction;
 use super::piece::{Piece, PieceType};
 use super::piece_move::{Move, MoveType, Square};
 use super::utils::flip_rank;
-use super::zobrist_hash::{get_zobrist_castling_rights, get_zobrist_en_passant_square, get_zobrist_hash, get_zobrist_side, get_zobrist_squares};
-use crate::evaluation::piece_square_tables::{endgame_position_value, midgame_position_value};
+use super::zobrist_hash::{get_material_hash, get_pawn_hash, get_zobrist_castling_rights, get_zobrist_en_passant_square, get_zobrist_hash, get_zobrist_material, get_zobrist_side, get_zobrist_squares};
+use crate::evaluation::nnue::{Accumulator, Network};
+use crate::evaluation::score::Score;
+use crate::evaluation::eval_params::{EvalParams, DEFAULT_EVAL_PARAMS};
 use crate::move_generation::attack_tables::*;
 use crate::search::transposition_table::*;
 use num_enum::UnsafeFromPrimitive;
@@ -12,9 +14,10 @@ use pyrrhic_rs::EngineAdapter;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::ops::{Index, IndexMut};
+use std::sync::Arc;
 use std::u64;
 
-const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
 pub const RANK_1: Bitboard = Bitboard(0xFF00000000000000);
 pub const RANK_2: Bitboard = Bitboard(0x00FF000000000000);
@@ -27,6 +30,12 @@ pub const RANK_8: Bitboard = Bitboard(0x00000000000000FF);
 pub const RANKS: [Bitboard; 8] = [RANK_1, RANK_2, RANK_3, RANK_4, RANK_5, RANK_6, RANK_7, RANK_8];
 pub const TOTAL_MATERIAL_STARTPOS: u32 = 16 * PieceType::Pawn.standard_value() + 4 * PieceType::Knight.standard_value() + 4 * PieceType::Bishop.standard_value() + 4 * PieceType::Rook.standard_value() + 2 * PieceType::Queen.standard_value();
 
+// Recomputes every incrementally updated field after each move and panics on a mismatch
+#[cfg(feature = "incremental-checks")]
+const CHECK_INCREMENTAL: bool = true;
+#[cfg(not(feature = "incremental-checks"))]
+const CHECK_INCREMENTAL: bool = false;
+
 const BLACK_QUEENSIDE_START_MASK: Bitboard = Bitboard((1 << 0) | (1 << 4)); // a8 and e8
 const BLACK_KINGSIDE_START_MASK: Bitboard = Bitboard((1 << 7) | (1 << 4)); // h8 and e8
 const WHITE_QUEENSIDE_START_MASK: Bitboard = Bitboard((1 << 56) | (1 << 60)); // a1 and e1
@@ -50,12 +59,15 @@ pub struct Board {
     pub piece_squares: [Bitboard; 12],
     pub absolute_pinned_squares: Bitboard,
     pub states: Vec<BoardState>,
-    pub material_balance: i32,
     pub total_material: u32,
-    pub midgame_position_balance: i32,
-    pub endgame_position_balance: i32,
+    pub score: Score,                     // Material and piece-square tables from white's perspective
+    pub phase: i32,                       // Non-pawn material by PieceType::phase, above MAX_PHASE after promotions
+    pub eval_params: Arc<EvalParams>,     // Weights behind the score
+    pub accumulator: Option<Accumulator>, // NNUE hidden layer, only maintained when a network is set
     //pub transposition_table: TranspositionTable,
     pub zobrist_hash: u64,
+    pub pawn_hash: u64,     // Zobrist key of the pawns alone
+    pub material_hash: u64, // Zobrist key of the number of pieces of each kind
     pub opening_move_count: u32,
     pub ply: u32,
     pub can_detect_threefold_repetition: bool,
@@ -75,6 +87,51 @@ impl Board {
         board.diagonal_pinmask = get_diagonal_rays(square);
         board
     }
+    // Checks the FEN before loading it, as from_fen panics on malformed input. The move counters
+    // may be left out, as some GUIs do.
+    pub fn try_from_fen(fen: &str) -> Result<Self, String> {
+        let mut fields: Vec<&str> = fen.split_whitespace().collect();
+        if !(4..=6).contains(&fields.len()) {
+            return Err(format!("invalid fen, expected 4 to 6 fields: {}", fen));
+        }
+        fields.resize(6, "0");
+        if fields[5] == "0" {
+            fields[5] = "1";
+        }
+
+        let ranks: Vec<&str> = fields[0].split('/').collect();
+        let valid_rank = |rank: &&str| {
+            let mut files = 0;
+            for char in rank.chars() {
+                match char {
+                    '1'..='8' => files += char.to_digit(10).unwrap(),
+                    'p' | 'n' | 'b' | 'r' | 'q' | 'k' | 'P' | 'N' | 'B' | 'R' | 'Q' | 'K' => files += 1,
+                    _ => return false,
+                }
+            }
+            files == 8
+        };
+        if ranks.len() != 8 || !ranks.iter().all(valid_rank) {
+            return Err(format!("invalid piece placement: {}", fields[0]));
+        }
+        if fields[0].matches('K').count() != 1 || fields[0].matches('k').count() != 1 {
+            return Err(format!("each side needs exactly one king: {}", fields[0]));
+        }
+        if !["w", "b"].contains(&fields[1]) {
+            return Err(format!("invalid side to move: {}", fields[1]));
+        }
+        if fields[2] != "-" && !fields[2].chars().all(|char| "KQkq".contains(char)) {
+            return Err(format!("invalid castling rights: {}", fields[2]));
+        }
+        let en_passant = fields[3].as_bytes();
+        if fields[3] != "-" && !(en_passant.len() == 2 && (b'a'..=b'h').contains(&en_passant[0]) && (en_passant[1] == b'3' || en_passant[1] == b'6')) {
+            return Err(format!("invalid en passant square: {}", fields[3]));
+        }
+        if fields[4].parse::<u8>().is_err() || fields[5].parse::<u32>().is_err() {
+            return Err(format!("invalid move counters: {} {}", fields[4], fields[5]));
+        }
+        Ok(Self::from_fen(&fields.join(" ")))
+    }
     pub fn fen(&self) -> String {
         let mut fen = "".to_string();
         let piece_types = HashMap::from([(PieceType::Pawn, 'p'), (PieceType::Knight, 'n'), (PieceType::Bishop, 'b'), (PieceType::Rook, 'r'), (PieceType::Queen, 'q'), (PieceType::King, 'k')]);
@@ -122,7 +179,7 @@ impl Board {
             no_castling = false;
             fen.push('k');
         }
-        if self.state().castling_rights[Side::Black].kingside {
+        if self.state().castling_rights[Side::Black].queenside {
             no_castling = false;
             fen.push('q');
         }
@@ -147,11 +204,89 @@ impl Board {
         fen.push_str(&(1 + self.ply / 2).to_string());
         fen
     }
+    // The same position with the colours swapped and the board flipped vertically, which every
+    // evaluation should score the same for the side to move
+    pub fn mirrored(&self) -> Board {
+        let fen = self.fen();
+        let fields: Vec<&str> = fen.split(' ').collect();
+        let swap_case = |string: &str| string.chars().map(|char| if char.is_uppercase() { char.to_ascii_lowercase() } else { char.to_ascii_uppercase() }).collect::<String>();
+        let placement: Vec<String> = fields[0].split('/').rev().map(swap_case).collect();
+        let side = if self.side == Side::White { "b" } else { "w" };
+        let en_passant = match fields[3] {
+            "-" => "-".to_string(),
+            square => format!("{}{}", &square[..1], 9 - square[1..].parse::<u32>().unwrap()),
+        };
+        let mut board = Board::from_fen(&format!("{} {} {} {} {} {}", placement.join("/"), side, swap_case(fields[2]), en_passant, fields[4], fields[5]));
+        board.set_eval_params(self.eval_params.clone());
+        board.set_network(self.accumulator.as_ref().map(|accumulator| accumulator.network().clone()));
+        board
+    }
+
+    // Compares every incrementally updated field with a recomputation from the squares
+    pub fn verify(&self) -> Result<(), String> {
+        fn check<T: PartialEq + std::fmt::Debug>(name: &str, actual: T, expected: T) -> Result<(), String> {
+            if actual == expected {
+                Ok(())
+            } else {
+                Err(format!("{} is {:?} but should be {:?}", name, actual, expected))
+            }
+        }
+        let mut fresh = Board { states: self.states.clone(), side: self.side, squares: self.squares, ..Board::default() };
+        fresh.initialize_bitboards();
+        fresh.set_eval_params(self.eval_params.clone());
+        for piece in self.squares.iter().flatten() {
+            fresh.total_material += piece.piece_type().standard_value();
+            fresh.phase += piece.piece_type().phase();
+        }
+
+        check("piece_squares", self.piece_squares, fresh.piece_squares)?;
+        check("side_squares", self.side_squares, fresh.side_squares)?;
+        check("occupied_squares", self.occupied_squares, fresh.occupied_squares)?;
+        check("score", self.score, fresh.score)?;
+        check("phase", self.phase, fresh.phase)?;
+        check("total_material", self.total_material, fresh.total_material)?;
+        check("zobrist_hash", self.zobrist_hash, get_zobrist_hash(&fresh))?;
+        check("state zobrist_hash", self.state().zobrist_hash, self.zobrist_hash)?;
+        check("pawn_hash", self.pawn_hash, get_pawn_hash(&fresh))?;
+        check("material_hash", self.material_hash, get_material_hash(&fresh))?;
+        if let Some(accumulator) = &self.accumulator {
+            if *accumulator != Accumulator::new(accumulator.network().clone(), &fresh) {
+                return Err("accumulator differs from a refresh".to_string());
+            }
+        }
+        Ok(())
+    }
+    #[inline(always)]
+    fn check_incremental(&self, operation: &str) {
+        if CHECK_INCREMENTAL {
+            if let Err(error) = self.verify() {
+                panic!("{} after {} in {}", error, operation, self.fen());
+            }
+        }
+    }
     pub fn start_pos() -> Self {
         let mut board = Self::from_fen(STARTING_FEN);
         return board;
     }
 
+    // Switches the hand crafted evaluation to other weights, recomputing the score that depends on them
+    pub fn set_eval_params(&mut self, eval_params: Arc<EvalParams>) {
+        self.eval_params = eval_params;
+        self.initialize_score();
+    }
+    fn initialize_score(&mut self) {
+        self.score = Score::default();
+        for square in 0..64 {
+            if let Some(piece) = self.squares[square] {
+                self.score += self.eval_params.piece_square_score(piece, square) * piece.side().factor();
+            }
+        }
+    }
+    // Switches evaluation to the given network, or back to the hand crafted evaluation for None
+    pub fn set_network(&mut self, network: Option<Arc<Network>>) {
+        self.accumulator = network.map(|network| Accumulator::new(network, self));
+    }
+
     pub fn friendly_squares(&self) -> Bitboard {
         self.side_squares[self.side]
     }
@@ -223,13 +358,14 @@ impl Board {
         self.initialize_bitboards();
         for square in 0..64 {
             if let Some(piece) = self.squares[square] {
-                self.material_balance += piece.piece_type().centipawns() * piece.side().factor();
-                self.midgame_position_balance += midgame_position_value(piece.piece_type(), square, piece.side()) * piece.side().factor();
-                self.endgame_position_balance += endgame_position_value(piece.piece_type(), square, piece.side()) * piece.side().factor();
                 self.total_material += piece.piece_type().standard_value();
+                self.phase += piece.piece_type().phase();
             }
         }
+        self.initialize_score();
         self.zobrist_hash = get_zobrist_hash(self);
+        self.pawn_hash = get_pawn_hash(self);
+        self.material_hash = get_material_hash(self);
         self.state_mut().zobrist_hash = self.zobrist_hash;
     }
     pub fn make_move(&mut self, mov: Move) {
@@ -322,6 +458,7 @@ impl Board {
         self.ply += 1;
         state.zobrist_hash = self.zobrist_hash;
         self.states.push(state);
+        self.check_incremental("make_move");
     }
     pub fn unmake_move(&mut self, mov: Move) {
         self.ply -= 1;
@@ -380,9 +517,10 @@ impl Board {
         }
 
         self.zobrist_hash ^= get_zobrist_castling_rights(castling_rights_bits_before) ^ get_zobrist_castling_rights(castling_rights_bits_after);
+        self.check_incremental("unmake_move");
     }
     pub fn make_null_move(&mut self) {
-        let state = BoardState::from_state(self.state());
+        let mut state = BoardState::from_state(self.state());
 
         let castling_rights_bits_before = Self::castling_rights_bits(self.state().castling_rights);
 
@@ -399,7 +537,9 @@ impl Board {
         self.absolute_pinned_squares = self.absolute_pins();
 
         self.ply += 1;
+        state.zobrist_hash = self.zobrist_hash;
         self.states.push(state);
+        self.check_incremental("make_null_move");
     }
 
     pub fn unmake_null_move(&mut self) {
@@ -420,6 +560,7 @@ impl Board {
         }
 
         self.zobrist_hash ^= get_zobrist_castling_rights(castling_rights_bits_before) ^ get_zobrist_castling_rights(castling_rights_bits_after);
+        self.check_incremental("unmake_null_move");
     }
     #[inline(always)]
     pub fn attacked(&self, square: usize) -> bool {
@@ -450,6 +591,10 @@ impl Board {
 
         attackers
     }
+    pub fn has_non_pawn_material(&self, side: Side) -> bool {
+        let pawns_and_king = self.piece_squares[Piece::new(PieceType::Pawn, side)] | self.piece_squares[Piece::new(PieceType::King, side)];
+        self.side_squares[side] != pawns_and_king
+    }
     pub fn in_check(&self) -> bool {
         let king_square = self.piece_squares[Piece::new(PieceType::King, self.side)].lsb();
         return self.attacked(king_square);
@@ -519,25 +664,37 @@ impl Board {
         self.side_squares[piece.side()].set_bit(square); // Update kingside castling right
         self.piece_squares[piece].set_bit(square);
         self.squares[square] = Some(piece);
-        self.midgame_position_balance += midgame_position_value(piece.piece_type(), square, piece.side()) * piece.side().factor();
-        self.endgame_position_balance += endgame_position_value(piece.piece_type(), square, piece.side()) * piece.side().factor();
-        self.material_balance += piece.piece_type().centipawns() * piece.side().factor();
+        self.score += self.eval_params.piece_square_score(piece, square) * piece.side().factor();
         self.total_material += piece.piece_type().standard_value();
+        self.phase += piece.piece_type().phase();
         self.zobrist_hash ^= get_zobrist_squares(square, piece);
+        if piece.piece_type() == PieceType::Pawn {
+            self.pawn_hash ^= get_zobrist_squares(square, piece);
+        }
+        self.material_hash ^= get_zobrist_material(piece, self.piece_squares[piece].count_ones() as usize - 1);
+        if let Some(accumulator) = &mut self.accumulator {
+            accumulator.add(piece, square);
+        }
     }
 
     #[inline(always)]
     fn clear_square(&mut self, square: usize) {
         let piece = self.squares[square].unwrap();
+        self.material_hash ^= get_zobrist_material(piece, self.piece_squares[piece].count_ones() as usize - 1);
         self.occupied_squares.clear_bit(square);
         self.side_squares[piece.side()].clear_bit(square);
         self.piece_squares[piece].clear_bit(square);
         self.squares[square] = None;
-        self.midgame_position_balance -= midgame_position_value(piece.piece_type(), square, piece.side()) * piece.side().factor();
-        self.endgame_position_balance -= endgame_position_value(piece.piece_type(), square, piece.side()) * piece.side().factor();
-        self.material_balance -= piece.piece_type().centipawns() * piece.side().factor();
+        self.score -= self.eval_params.piece_square_score(piece, square) * piece.side().factor();
         self.total_material -= piece.piece_type().standard_value();
+        self.phase -= piece.piece_type().phase();
         self.zobrist_hash ^= get_zobrist_squares(square, piece);
+        if piece.piece_type() == PieceType::Pawn {
+            self.pawn_hash ^= get_zobrist_squares(square, piece);
+        }
+        if let Some(accumulator) = &mut self.accumulator {
+            accumulator.remove(piece, square);
+        }
     }
     #[inline(always)]
     fn checkmask(&mut self) -> Bitboard {
@@ -649,12 +806,15 @@ impl Default for Board {
             piece_squares: [Bitboard(0); 12],
             absolute_pinned_squares: Bitboard(0),
             states: vec![BoardState::default()],
-            material_balance: 0,
-            midgame_position_balance: 0,
-            endgame_position_balance: 0,
+            score: Score::default(),
+            phase: 0,
+            eval_params: DEFAULT_EVAL_PARAMS.clone(),
+            accumulator: None,
             total_material: 0,
             //transposition_table: TranspositionTable::default(),
             zobrist_hash: 0,
+            pawn_hash: 0,
+            material_hash: 0,
             opening_move_count: 0,
             ply: 0,
             orthogonal_pinmask: Bitboard(0),
@@ -775,10 +935,39 @@ impl Default for BoardState {
     }
 }
 
+// Plays random legal games from the benchmark positions, calling back after every move made or
+// taken back
+#[cfg(test)]
+pub(crate) fn random_games(games_per_position: usize, max_plies: usize, network: Option<Arc<Network>>, mut visit: impl FnMut(&mut Board)) {
+    use crate::move_generation::generate_moves;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(0xC0FFEE);
+    for fen in crate::bench::BENCHMARK_FENS {
+        for _ in 0..games_per_position {
+            let mut board = Board::from_fen(fen);
+            board.set_network(network.clone());
+            let mut moves = Vec::new();
+            while moves.len() < max_plies {
+                let Some(&mov) = generate_moves(&board).choose(&mut rng) else { break };
+                board.make_move(mov);
+                moves.push(mov);
+                visit(&mut board);
+            }
+            while let Some(mov) = moves.pop() {
+                board.unmake_move(mov);
+                visit(&mut board);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{board, move_generation::generate_moves};
+    use rand::{Rng, SeedableRng};
 
     #[test]
     fn test_load_fen() {
@@ -809,6 +998,24 @@ mod tests {
         assert_eq!(squares, board.squares);
     }
     #[test]
+    fn rejects_malformed_fens() {
+        let board = Board::try_from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6").unwrap();
+        assert_eq!(board.fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 1");
+        for fen in [
+            "",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqqbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkx - 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e4 0 1",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1",
+        ] {
+            assert!(Board::try_from_fen(fen).is_err(), "{} accepted", fen);
+        }
+    }
+    #[test]
     fn sets_correct_bitboards_from_squares() {
         let board = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1");
         let white_pawn_bitboard = board.piece_squares[Piece::new(PieceType::Pawn, Side::White)];
@@ -844,6 +1051,58 @@ mod tests {
         }
     }
     #[test]
+    fn pawn_and_material_hashes() {
+        // Captures, promotions, castling and en passant all change them
+        let mut board = Board::from_fen("r3k2r/p1pPqpb1/bn2pnp1/4N3/1pP1P3/2N2Q2/PP1BBPpP/R3K2R b KQkq c3 0 1");
+        let (pawn_hash, material_hash) = (board.pawn_hash, board.material_hash);
+        for mov in generate_moves(&board) {
+            board.make_move(mov);
+            for reply in generate_moves(&board) {
+                board.make_move(reply);
+                assert_eq!(board.pawn_hash, get_pawn_hash(&board));
+                assert_eq!(board.material_hash, get_material_hash(&board));
+                board.unmake_move(reply);
+            }
+            board.make_null_move();
+            assert_eq!(board.material_hash, get_material_hash(&board));
+            board.unmake_null_move();
+            board.unmake_move(mov);
+        }
+        assert_eq!((board.pawn_hash, board.material_hash), (pawn_hash, material_hash));
+
+        // Moving a piece keeps both keys, a promotion changes both
+        let moved = Board::from_fen("r3k2r/p1pPqpb1/bn2pnp1/4N3/1pP1P3/2N2Q2/PP1BBPpP/R3K1R1 b Qkq - 0 1");
+        assert_eq!(moved.material_hash, material_hash);
+        assert_eq!(moved.pawn_hash, pawn_hash);
+        let promoted = Board::from_fen("r3k2r/p1pPqpb1/bn2pnp1/4N3/1pP1P3/2N2Q2/PP1BBP1P/R3K1q1 w Qkq - 0 1");
+        assert_ne!(promoted.material_hash, material_hash);
+        assert_ne!(promoted.pawn_hash, pawn_hash);
+    }
+    fn check_incremental_fields(board: &mut Board) {
+        board.verify().unwrap_or_else(|error| panic!("{} in {}", error, board.fen()));
+        assert_eq!(Board::from_fen(&board.fen()).zobrist_hash, board.zobrist_hash, "{}", board.fen());
+        if !board.in_check() {
+            board.make_null_move();
+            board.verify().unwrap_or_else(|error| panic!("{} after a null move in {}", error, board.fen()));
+            board.unmake_null_move();
+        }
+    }
+    #[test]
+    fn random_games_keep_incremental_fields() {
+        let mut positions = 0;
+        random_games(2, 80, None, |board| {
+            check_incremental_fields(board);
+            positions += 1;
+        });
+        assert!(positions > 10000);
+
+        // Random weights, so a wrong update shows up in the accumulator
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(7);
+        let bytes: Vec<u8> = (0..(768 * 8 + 8 * 3 + 1) * 2).map(|_| rng.gen()).collect();
+        let network = Arc::new(Network::from_bytes(&bytes).unwrap());
+        random_games(1, 40, Some(network), check_incremental_fields);
+    }
+    #[test]
     fn engine_adapter() {
         let board = Board::from_fen("4k3/8/3QK3/8/8/8/8/8 w - - 0 1");
         println!("{}", Bitboard(Board::queen_attacks(board.piece_squares[Piece::WhiteQueen].lsb() as u64, board.occupied_squares.0)));
@@ -867,6 +1126,12 @@ mod tests {
         assert_eq!(before, board.zobrist_hash);
     }
     #[test]
+    fn test_non_pawn_material() {
+        let board = Board::from_fen("4k3/pp6/8/8/8/8/6PP/4KN2 w - - 0 1");
+        assert!(board.has_non_pawn_material(Side::White));
+        assert!(!board.has_non_pawn_material(Side::Black));
+    }
+    #[test]
     fn test_repetition() {
         let mut board = Board::from_fen("4k3/2q5/8/8/8/8/2Q5/4K3 w - - 0 1");
         let moves = vec![
@@ -39,8 +39,8 @@ impl Direction {
     pub const fn diagonal() -> [Direction; 4] {
         [Direction::NorthWest, Direction::NorthEast, Direction::SouthWest, Direction::SouthEast]
     }
-    pub fn opposite(self) -> Direction {
-        unsafe { UnsafeFromPrimitive::unchecked_transmute_from(7 - self as u8) }
+    pub const fn opposite(self) -> Direction {
+        Direction::all()[7 - self as usize]
     }
     pub fn up(side: Side) -> Direction {
         unsafe { UnsafeFromPrimitive::unchecked_transmute_from(7 * side as u8) }
@@ -3,6 +3,8 @@ pub mod board;
 pub mod direction;
 pub mod piece;
 pub mod piece_move;
+pub mod san;
+pub mod see;
 pub mod zobrist_hash;
 pub mod utils;
 pub use board::*;
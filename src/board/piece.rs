@@ -33,6 +33,21 @@ impl Piece {
     pub fn side(&self) -> Side {
         unsafe { Side::unchecked_transmute_from((*self as u8) & Piece::SIDE_MASK) }
     }
+    // FEN letter of the piece, uppercase for white
+    pub fn to_char(&self) -> char {
+        let piece_char = match self.piece_type() {
+            PieceType::Pawn => 'p',
+            PieceType::Knight => 'n',
+            PieceType::Bishop => 'b',
+            PieceType::Rook => 'r',
+            PieceType::Queen => 'q',
+            PieceType::King => 'k',
+        };
+        match self.side() {
+            Side::White => piece_char.to_ascii_uppercase(),
+            Side::Black => piece_char,
+        }
+    }
     pub fn all() -> [Piece; 12] {
         [
             Piece::WhitePawn,
@@ -83,19 +98,24 @@ impl PieceType {
             PieceType::King => 0,
         }
     }
+    // Weight in the game phase, which adds up to MAX_PHASE for the non-pawn material of the starting position
     pub const fn phase(&self) -> i32 {
         match self {
-            PieceType::Pawn => 1,
-            PieceType::Knight => 2,
-            PieceType::Bishop => 2,
-            PieceType::Rook => 3,
-            PieceType::Queen => 5,
+            PieceType::Pawn => 0,
+            PieceType::Knight => 1,
+            PieceType::Bishop => 1,
+            PieceType::Rook => 2,
+            PieceType::Queen => 4,
             PieceType::King => 0,
         }
     }
     pub fn is_slider(&self) -> bool {
         matches!(self, PieceType::Bishop | PieceType::Rook | PieceType::Queen)
     }
+    // In discriminant order, so indexing with `piece_type as usize` gives the piece type back
+    pub fn all() -> [PieceType; 6] {
+        [PieceType::Pawn, PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen, PieceType::King]
+    }
 }
 
 impl<T, const N: usize> Index<Piece> for [T; N] {
@@ -2,6 +2,7 @@ use num_enum::UnsafeFromPrimitive;
 
 use crate::board::piece::PieceType;
 use crate::board::{square_from_string, Board};
+use crate::move_generation::generate_moves;
 use std::collections::HashMap;
 use std::fmt::Display;
 
@@ -41,6 +42,9 @@ pub struct Move {
 }
 
 impl Move {
+    // Passes the turn, written `0000` in UCI. Never a legal move as it starts and ends on a8.
+    pub const NULL: Move = Move { bits: 0 };
+
     pub fn new(start_square: usize, end_square: usize, move_type: MoveType) -> Move {
         //assert!(start_square < 64, "start square can't be larger than 63");
         //assert!(end_square < 64, "end square can't be larger than 63");
@@ -49,6 +53,13 @@ impl Move {
             bits: ((move_type as u16) << 12) | ((end_square as u16) << 6) | (start_square as u16),
         }
     }
+    // Packed form for storing moves compactly, only to be turned back into a move with from_bits
+    pub(crate) const fn bits(self) -> u16 {
+        self.bits
+    }
+    pub(crate) const fn from_bits(bits: u16) -> Move {
+        Move { bits }
+    }
 
     pub fn from(&self) -> Square {
         (self.bits & 0b111111) as Square
@@ -97,6 +108,9 @@ impl Move {
 
 impl Display for Move {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if *self == Move::NULL {
+            return write!(f, "0000");
+        }
         let piece_chars = HashMap::from([(PieceType::Knight, 'n'), (PieceType::Bishop, 'b'), (PieceType::Rook, 'r'), (PieceType::Queen, 'q')]);
         let files = "abcdefgh";
         let start_file = files.chars().nth(self.from() % 8).unwrap();
@@ -112,6 +126,68 @@ impl Display for Move {
     }
 }
 
+/// Why a move in long algebraic notation could not be played in a position.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MoveParseError {
+    InvalidFormat(String), // Not two squares and an optional promotion piece
+    Illegal(String),
+    NullMoveInCheck,
+}
+
+impl Display for MoveParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveParseError::InvalidFormat(mov) => write!(f, "invalid move: {}", mov),
+            MoveParseError::Illegal(mov) => write!(f, "illegal move: {}", mov),
+            MoveParseError::NullMoveInCheck => write!(f, "null move while in check"),
+        }
+    }
+}
+
+impl std::error::Error for MoveParseError {}
+
+impl Board {
+    // Legal move in UCI notation, like `e2e4`, `e7e8q` or `0000` for a null move. Castling may also
+    // be written as the king taking its own rook, as Chess960 GUIs do.
+    pub fn parse_uci_move(&self, string: &str) -> Result<Move, MoveParseError> {
+        if string == "0000" {
+            return if self.in_check() { Err(MoveParseError::NullMoveInCheck) } else { Ok(Move::NULL) };
+        }
+        let invalid = || MoveParseError::InvalidFormat(string.to_string());
+        let bytes = string.as_bytes();
+        if !(4..=5).contains(&bytes.len()) {
+            return Err(invalid());
+        }
+        let square = |index: usize| {
+            let (file, rank) = (bytes[index], bytes[index + 1]);
+            ((b'a'..=b'h').contains(&file) && (b'1'..=b'8').contains(&rank)).then(|| (b'8' - rank) as usize * 8 + (file - b'a') as usize)
+        };
+        let (Some(from), Some(to)) = (square(0), square(2)) else {
+            return Err(invalid());
+        };
+        let promotion = match bytes.get(4) {
+            None => None,
+            Some(b'n') => Some(PieceType::Knight),
+            Some(b'b') => Some(PieceType::Bishop),
+            Some(b'r') => Some(PieceType::Rook),
+            Some(b'q') => Some(PieceType::Queen),
+            Some(_) => return Err(invalid()),
+        };
+
+        let moves = generate_moves(self);
+        let mov = match (self.squares[from], self.squares[to]) {
+            (Some(king), Some(rook))
+                if king.piece_type() == PieceType::King && rook.piece_type() == PieceType::Rook && king.side() == self.side && rook.side() == self.side =>
+            {
+                let castle = if to > from { MoveType::KingsideCastle } else { MoveType::QueensideCastle };
+                moves.into_iter().find(|mov| mov.move_type() == castle && mov.from() == from)
+            }
+            _ => moves.into_iter().find(|mov| mov.from() == from && mov.to() == to && mov.move_type().promotion_piece() == promotion),
+        };
+        mov.ok_or_else(|| MoveParseError::Illegal(string.to_string()))
+    }
+}
+
 impl PartialOrd for Move {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         Some(self.cmp(other))
@@ -135,5 +211,30 @@ mod test {
     fn parse_to_long_algebraic_notation() {
         let mov = Move::new(0, 4, MoveType::Normal);
         assert_eq!(mov.to_string(), "a8e8");
+        assert_eq!(Move::NULL.to_string(), "0000");
+    }
+
+    #[test]
+    fn parses_uci_moves() {
+        let board = Board::from_fen("r3k2r/pPppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        assert_eq!(board.parse_uci_move("e1g1"), Ok(Move::new(60, 62, MoveType::KingsideCastle)));
+        assert_eq!(board.parse_uci_move("e1h1"), Ok(Move::new(60, 62, MoveType::KingsideCastle)));
+        assert_eq!(board.parse_uci_move("e1a1"), Ok(Move::new(60, 58, MoveType::QueensideCastle)));
+        assert_eq!(board.parse_uci_move("b7a8n"), Ok(Move::new(9, 0, MoveType::KnightPromotion)));
+        assert_eq!(board.parse_uci_move("b7b8q").unwrap().to_string(), "b7b8q");
+        assert_eq!(board.parse_uci_move("0000"), Ok(Move::NULL));
+        for mov in generate_moves(&board) {
+            assert_eq!(board.parse_uci_move(&mov.to_string()), Ok(mov));
+        }
+
+        assert_eq!(board.parse_uci_move("b7b8"), Err(MoveParseError::Illegal("b7b8".to_string())));
+        assert_eq!(board.parse_uci_move("e1e3"), Err(MoveParseError::Illegal("e1e3".to_string())));
+        assert_eq!(board.parse_uci_move("e8h8"), Err(MoveParseError::Illegal("e8h8".to_string())));
+        assert_eq!(board.parse_uci_move("e8a8"), Err(MoveParseError::Illegal("e8a8".to_string())));
+        for mov in ["", "e2", "e2e4e", "i2i4", "e0e4", "b7b8k", "é2e4"] {
+            assert_eq!(board.parse_uci_move(mov), Err(MoveParseError::InvalidFormat(mov.to_string())));
+        }
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/4K2r w - - 0 1");
+        assert_eq!(board.parse_uci_move("0000"), Err(MoveParseError::NullMoveInCheck));
     }
 }
@@ -0,0 +1,112 @@
+use crate::board::piece::PieceType;
+use crate::board::piece_move::{Move, MoveType};
+use crate::board::Board;
+use crate::move_generation::generate_moves;
+
+pub fn square_name(square: usize) -> String {
+    format!("{}{}", (b'a' + (square % 8) as u8) as char, 8 - square / 8)
+}
+
+fn piece_letter(piece_type: PieceType) -> char {
+    match piece_type {
+        PieceType::Pawn => 'P',
+        PieceType::Knight => 'N',
+        PieceType::Bishop => 'B',
+        PieceType::Rook => 'R',
+        PieceType::Queen => 'Q',
+        PieceType::King => 'K',
+    }
+}
+
+impl Board {
+    // Standard algebraic notation of a legal move, like `Nbd7`, `exd6`, `e8=Q+` or `O-O#`
+    pub fn san(&mut self, mov: Move) -> String {
+        let mut san = self.san_without_check(mov);
+        self.make_move(mov);
+        if self.in_check() {
+            san.push(if generate_moves(self).is_empty() { '#' } else { '+' });
+        }
+        self.unmake_move(mov);
+        san
+    }
+
+    // Legal move written in standard algebraic notation, ignoring check marks and annotations
+    pub fn parse_san(&self, san: &str) -> Option<Move> {
+        let san = san.trim_end_matches(['+', '#', '!', '?']).replace('0', "O");
+        generate_moves(self).into_iter().find(|&mov| self.san_without_check(mov) == san)
+    }
+
+    fn san_without_check(&self, mov: Move) -> String {
+        match mov.move_type() {
+            MoveType::KingsideCastle => return "O-O".to_string(),
+            MoveType::QueensideCastle => return "O-O-O".to_string(),
+            _ => {}
+        }
+        let piece_type = self.squares[mov.from()].unwrap().piece_type();
+        let capture = self.is_capture(mov) || mov.move_type() == MoveType::EnPassant;
+        let mut san = String::new();
+        if piece_type == PieceType::Pawn {
+            if capture {
+                san.push((b'a' + (mov.from() % 8) as u8) as char);
+            }
+        } else {
+            san.push(piece_letter(piece_type));
+            // Only as much of the origin as is needed to tell apart pieces reaching the same square
+            let others: Vec<Move> = generate_moves(self)
+                .into_iter()
+                .filter(|other| other.to() == mov.to() && other.from() != mov.from() && self.squares[other.from()].unwrap().piece_type() == piece_type)
+                .collect();
+            if !others.is_empty() {
+                let from = square_name(mov.from());
+                if others.iter().all(|other| other.from() % 8 != mov.from() % 8) {
+                    san.push_str(&from[..1]);
+                } else if others.iter().all(|other| other.from() / 8 != mov.from() / 8) {
+                    san.push_str(&from[1..]);
+                } else {
+                    san.push_str(&from);
+                }
+            }
+        }
+        if capture {
+            san.push('x');
+        }
+        san.push_str(&square_name(mov.to()));
+        if let Some(promotion) = mov.move_type().promotion_piece() {
+            san.push('=');
+            san.push(piece_letter(promotion));
+        }
+        san
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_and_parses_san() {
+        let mut board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        let mut sans: Vec<String> = generate_moves(&board).into_iter().map(|mov| board.san(mov)).collect();
+        sans.sort();
+        for san in ["O-O", "O-O-O", "Nxf7", "Qxh3", "Bxa6", "dxe6", "Rb1", "Kd1", "Ng4", "Nb1", "Nd1"] {
+            assert!(sans.contains(&san.to_string()), "{} missing from {:?}", san, sans);
+        }
+        for san in sans.iter() {
+            let mov = board.parse_san(san).unwrap();
+            assert_eq!(&board.san(mov), san);
+        }
+
+        // Disambiguation by file, by rank and by both
+        let board = Board::from_fen("4k3/8/8/1N3N2/8/1N6/8/4K3 w - - 0 1");
+        assert_eq!(board.san_without_check(board.parse_san("Nfd4").unwrap()), "Nfd4");
+        assert_eq!(board.san_without_check(board.parse_san("N3d4").unwrap()), "N3d4");
+        assert_eq!(board.san_without_check(board.parse_san("Nb5d4").unwrap()), "Nb5d4");
+        assert_eq!(board.parse_san("Nd4"), None);
+
+        let mut board = Board::from_fen("7k/4P3/6K1/8/8/8/8/8 w - - 0 1");
+        assert_eq!(board.san(board.parse_san("e8=Q").unwrap()), "e8=Q#");
+        let mut board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        assert_eq!(board.san(board.parse_san("exd6").unwrap()), "exd6");
+        assert_eq!(board.parse_san("0-0"), None);
+    }
+}
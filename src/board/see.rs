@@ -0,0 +1,119 @@
+use crate::board::bitboard::Bitboard;
+use crate::board::piece::{Piece, PieceType};
+use crate::board::piece_move::{Move, MoveType};
+use crate::board::{Board, Side};
+use crate::move_generation::attack_tables::{bishop_attacks, get_king_attack_mask, get_knight_attack_mask, get_pawn_attack, rook_attacks};
+
+fn see_value(piece_type: PieceType) -> i32 {
+    match piece_type {
+        PieceType::King => 10000,
+        _ => piece_type.standard_value() as i32 * 100,
+    }
+}
+
+impl Board {
+    // Pieces of both sides attacking a square, with sliders seen through the squares missing
+    // from the occupancy
+    fn attackers_to(&self, square: usize, occupied: Bitboard) -> Bitboard {
+        let pieces = |piece_type: PieceType| self.piece_squares[Piece::new(piece_type, Side::White)] | self.piece_squares[Piece::new(piece_type, Side::Black)];
+        let diagonal = pieces(PieceType::Bishop) | pieces(PieceType::Queen);
+        let orthogonal = pieces(PieceType::Rook) | pieces(PieceType::Queen);
+        let attackers = (get_pawn_attack(Side::White, square) & self.piece_squares[Piece::WhitePawn])
+            | (get_pawn_attack(Side::Black, square) & self.piece_squares[Piece::BlackPawn])
+            | (get_knight_attack_mask(square) & pieces(PieceType::Knight))
+            | (get_king_attack_mask(square) & pieces(PieceType::King))
+            | (bishop_attacks(square, occupied) & diagonal)
+            | (rook_attacks(square, occupied) & orthogonal);
+        attackers & occupied
+    }
+
+    // Static exchange evaluation, the material in centipawns the side to move wins when both sides
+    // keep recapturing on the target square with their least valuable piece, ignoring pins
+    pub fn see(&self, mov: Move) -> i32 {
+        let (from, to) = (mov.from(), mov.to());
+        if matches!(mov.move_type(), MoveType::KingsideCastle | MoveType::QueensideCastle) {
+            return 0;
+        }
+        let mut occupied = self.occupied_squares;
+        let mut attacker = self.squares[from].unwrap().piece_type();
+        let mut gain = [0; 32];
+        gain[0] = match mov.move_type() {
+            MoveType::EnPassant => {
+                occupied.clear_bit(if self.side == Side::White { to + 8 } else { to - 8 });
+                see_value(PieceType::Pawn)
+            }
+            _ => self.squares[to].map_or(0, |piece| see_value(piece.piece_type())),
+        };
+        if let Some(promotion) = mov.move_type().promotion_piece() {
+            gain[0] += see_value(promotion) - see_value(PieceType::Pawn);
+            attacker = promotion;
+        }
+        occupied.clear_bit(from);
+
+        let mut side = self.side.enemy();
+        let mut depth = 0;
+        loop {
+            let attackers = self.attackers_to(to, occupied);
+            let own = attackers & self.side_squares[side];
+            let Some((square, piece_type)) = PieceType::all().into_iter().find_map(|piece_type| {
+                let pieces = own & self.piece_squares[Piece::new(piece_type, side)];
+                (pieces != 0).then(|| (pieces.lsb(), piece_type))
+            }) else {
+                break;
+            };
+            // The king can't capture onto a square the other side still defends
+            if piece_type == PieceType::King && (attackers & self.side_squares[side.enemy()]) != 0 {
+                break;
+            }
+            depth += 1;
+            gain[depth] = see_value(attacker) - gain[depth - 1];
+            attacker = piece_type;
+            occupied.clear_bit(square);
+            side = side.enemy();
+        }
+        // Either side may stop recapturing when it would lose material by going on
+        while depth > 0 {
+            gain[depth - 1] = -i32::max(-gain[depth - 1], gain[depth]);
+            depth -= 1;
+        }
+        gain[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evaluates_exchanges() {
+        // Rook takes a pawn defended by nothing, then a pawn defended by a pawn
+        let board = Board::from_fen("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1");
+        assert_eq!(board.see(board.parse_uci_move("e1e5").unwrap()), 100);
+        let board = Board::from_fen("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1");
+        assert_eq!(board.see(board.parse_uci_move("d3e5").unwrap()), -200);
+
+        // Sliders behind the first attacker join in once it has captured
+        let board = Board::from_fen("3rk3/8/8/3p4/8/8/3R4/3QK3 w - - 0 1");
+        assert_eq!(board.see(board.parse_uci_move("d2d5").unwrap()), 100);
+        let board = Board::from_fen("3qk3/3r4/8/3p4/8/8/3R4/3QK3 w - - 0 1");
+        assert_eq!(board.see(board.parse_uci_move("d2d5").unwrap()), -400);
+
+        // Quiet moves only lose material on attacked squares
+        let board = Board::from_fen("4k3/8/2p5/8/8/8/8/1N2K3 w - - 0 1");
+        assert_eq!(board.see(board.parse_uci_move("b1d2").unwrap()), 0);
+        let board = Board::from_fen("4k3/8/2p5/8/3N4/8/8/4K3 w - - 0 1");
+        assert_eq!(board.see(board.parse_uci_move("d4b5").unwrap()), -300);
+
+        // The king only recaptures pieces that are no longer defended
+        let board = Board::from_fen("4k3/4p3/8/8/8/8/4R3/4K3 w - - 0 1");
+        assert_eq!(board.see(board.parse_uci_move("e2e7").unwrap()), -400);
+        let board = Board::from_fen("4k3/4p3/8/8/8/4R3/4R3/4K3 w - - 0 1");
+        assert_eq!(board.see(board.parse_uci_move("e3e7").unwrap()), 100);
+
+        let board = Board::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        assert_eq!(board.see(board.parse_uci_move("e5d6").unwrap()), 100);
+        let board = Board::from_fen("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(board.see(board.parse_uci_move("b7b8q").unwrap()), -100);
+        assert_eq!(board.see(board.parse_uci_move("b7a8q").unwrap()), 1300);
+    }
+}
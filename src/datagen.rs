@@ -0,0 +1,390 @@
+use std::io::{self, Write};
+
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::board::piece::{Piece, PieceType};
+use crate::board::piece_move::MoveType;
+use crate::board::{Board, Side};
+use crate::move_generation::generate_moves;
+use crate::search::search::TB_WIN_THRESHOLD;
+use crate::search::{Search, SearchParams};
+use pyrrhic_rs::WdlProbeResult;
+
+pub const PACKED_BOARD_SIZE: usize = 32;
+
+/// Limits of the self-play games.
+#[derive(Clone, Copy)]
+pub struct DatagenConfig {
+    pub nodes: u32,             // Nodes searched for every move
+    pub random_plies: usize,    // Random moves played from the start position
+    pub max_opening_score: i32, // Openings scored higher than this are too one-sided to be played
+    pub adjudication: Adjudication,
+}
+
+impl Default for DatagenConfig {
+    fn default() -> Self {
+        Self {
+            nodes: 5000,
+            random_plies: 8,
+            max_opening_score: 400,
+            adjudication: Adjudication::default(),
+        }
+    }
+}
+
+/// When a game is ended early because the scores show it is clearly won or drawn.
+#[derive(Clone, Copy)]
+pub struct Adjudication {
+    pub resign_score: i32,     // A side wins once its score stays above this
+    pub resign_plies: usize,   // for this many plies in a row
+    pub draw_score: i32,       // The game is drawn once the score stays within this
+    pub draw_plies: usize,     // for this many plies in a row
+    pub draw_min_plies: usize, // but not before this many plies have been played
+}
+
+impl Default for Adjudication {
+    fn default() -> Self {
+        Self {
+            resign_score: 1000,
+            resign_plies: 4,
+            draw_score: 10,
+            draw_plies: 10,
+            draw_min_plies: 80,
+        }
+    }
+}
+
+/// Scores seen so far in a game, for adjudicating it.
+pub struct ScoreTracker {
+    adjudication: Adjudication,
+    plies: usize,
+    winning_plies: [usize; 2],
+    drawn_plies: usize,
+}
+
+impl ScoreTracker {
+    pub fn new(adjudication: Adjudication) -> Self {
+        Self { adjudication, plies: 0, winning_plies: [0; 2], drawn_plies: 0 }
+    }
+
+    // Adds the score of the next ply from white's point of view, returning the result once the
+    // game can be adjudicated
+    pub fn update(&mut self, white_score: i32) -> Option<GameResult> {
+        let adjudication = &self.adjudication;
+        for side in [Side::White, Side::Black] {
+            let side_score = if side == Side::White { white_score } else { -white_score };
+            self.winning_plies[side] = if side_score >= adjudication.resign_score { self.winning_plies[side] + 1 } else { 0 };
+            if self.winning_plies[side] >= adjudication.resign_plies {
+                return Some(GameResult::win_for(side));
+            }
+        }
+        self.drawn_plies = if self.plies >= adjudication.draw_min_plies && white_score.abs() <= adjudication.draw_score { self.drawn_plies + 1 } else { 0 };
+        self.plies += 1;
+        (self.drawn_plies >= adjudication.draw_plies).then_some(GameResult::Draw)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameResult {
+    WhiteWin,
+    Draw,
+    BlackWin,
+}
+
+impl GameResult {
+    pub fn win_for(side: Side) -> Self {
+        match side {
+            Side::White => Self::WhiteWin,
+            Side::Black => Self::BlackWin,
+        }
+    }
+    // 1.0, 0.5 or 0.0 from white's point of view
+    pub fn white_score(self) -> f32 {
+        match self {
+            Self::WhiteWin => 1.0,
+            Self::Draw => 0.5,
+            Self::BlackWin => 0.0,
+        }
+    }
+    // 0 for a loss, 1 for a draw and 2 for a win of the given side
+    fn relative(self, side: Side) -> u8 {
+        match (self, side) {
+            (Self::Draw, _) => 1,
+            (Self::WhiteWin, Side::White) | (Self::BlackWin, Side::Black) => 2,
+            _ => 0,
+        }
+    }
+}
+
+/// 32 byte position with the side to move as white, laid out like the `ChessBoard` of the bullet
+/// trainer: the occupied squares from a1, a nibble for every occupied square in that order with
+/// the piece type plus 8 for the opponent's pieces, the score in centipawns and the result for the
+/// side to move, both king squares with the opponent's seen from its own side, and 3 unused bytes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct PackedBoard {
+    pub occupancy: u64,
+    pub pieces: [u8; 16],
+    pub score: i16,
+    pub result: u8, // 0 for a loss, 1 for a draw and 2 for a win
+    pub king_square: u8,
+    pub opponent_king_square: u8,
+}
+
+impl PackedBoard {
+    // Score from the side to move's point of view, the result is filled in once the game is over
+    pub fn new(board: &Board, score: i32) -> Self {
+        let mut packed = Self { score: score.clamp(i16::MIN as i32, i16::MAX as i32) as i16, ..Default::default() };
+        let mut count = 0;
+        for relative_square in 0..64 {
+            // Squares are numbered from a8, so white flips the rank and black keeps it
+            let square = match board.side {
+                Side::White => relative_square ^ 56,
+                Side::Black => relative_square,
+            };
+            let Some(piece) = board.squares[square] else { continue };
+            let opponent = piece.side() != board.side;
+            packed.occupancy |= 1 << relative_square;
+            packed.pieces[count / 2] |= (piece.piece_type() as u8 | (opponent as u8) << 3) << (4 * (count % 2));
+            count += 1;
+            if piece.piece_type() == PieceType::King {
+                if opponent {
+                    packed.opponent_king_square = relative_square as u8 ^ 56;
+                } else {
+                    packed.king_square = relative_square as u8;
+                }
+            }
+        }
+        packed
+    }
+
+    pub fn to_bytes(&self) -> [u8; PACKED_BOARD_SIZE] {
+        let mut bytes = [0; PACKED_BOARD_SIZE];
+        bytes[0..8].copy_from_slice(&self.occupancy.to_le_bytes());
+        bytes[8..24].copy_from_slice(&self.pieces);
+        bytes[24..26].copy_from_slice(&self.score.to_le_bytes());
+        bytes[26] = self.result;
+        bytes[27] = self.king_square;
+        bytes[28] = self.opponent_king_square;
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; PACKED_BOARD_SIZE]) -> Self {
+        Self {
+            occupancy: u64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+            pieces: bytes[8..24].try_into().unwrap(),
+            score: i16::from_le_bytes([bytes[24], bytes[25]]),
+            result: bytes[26],
+            king_square: bytes[27],
+            opponent_king_square: bytes[28],
+        }
+    }
+
+    // The position with the side to move as white, without castling rights or en passant square
+    pub fn to_board(&self) -> Board {
+        let mut squares = [None; 64];
+        let mut occupancy = self.occupancy;
+        let mut count = 0;
+        while occupancy != 0 {
+            let relative_square = occupancy.trailing_zeros() as usize;
+            let nibble = self.pieces[count / 2] >> (4 * (count % 2)) & 0xF;
+            let side = if nibble & 8 != 0 { Side::Black } else { Side::White };
+            squares[relative_square ^ 56] = Some(Piece::new(PieceType::all()[(nibble & 7) as usize], side));
+            occupancy &= occupancy - 1;
+            count += 1;
+        }
+
+        let mut placement = String::new();
+        for rank in squares.chunks(8) {
+            let mut empty = 0;
+            for square in rank {
+                match square {
+                    Some(piece) => {
+                        if empty > 0 {
+                            placement.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        placement.push(piece.to_char());
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                placement.push_str(&empty.to_string());
+            }
+            placement.push('/');
+        }
+        placement.pop();
+        Board::from_fen(&format!("{} w - - 0 1", placement))
+    }
+}
+
+/// Quiet position of a self-play game.
+pub struct Sample {
+    pub fen: String,
+    pub score: i32, // Search score from white's point of view
+    pub side: Side,
+    pub packed: PackedBoard,
+}
+
+pub struct Game {
+    pub samples: Vec<Sample>,
+    pub result: GameResult,
+}
+
+impl Game {
+    // One `<fen> | <score> | <result>` line per position, both from white's point of view
+    pub fn write_text(&self, output: &mut impl Write) -> io::Result<()> {
+        for sample in self.samples.iter() {
+            writeln!(output, "{} | {} | {:.1}", sample.fen, sample.score, self.result.white_score())?;
+        }
+        Ok(())
+    }
+
+    pub fn write_binary(&self, output: &mut impl Write) -> io::Result<()> {
+        for sample in self.samples.iter() {
+            let packed = PackedBoard { result: self.result.relative(sample.side), ..sample.packed };
+            output.write_all(&packed.to_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+// Neither side can possibly checkmate
+fn insufficient_material(board: &Board) -> bool {
+    match board.occupied_squares.count_ones() {
+        2 => true,
+        3 => [PieceType::Knight, PieceType::Bishop].into_iter().any(|piece_type| (board.piece_squares[Piece::new(piece_type, Side::White)] | board.piece_squares[Piece::new(piece_type, Side::Black)]).0 != 0),
+        _ => false,
+    }
+}
+
+// Result by the rules: checkmate, stalemate, threefold repetition, the fifty move rule or
+// insufficient material
+pub fn game_over(board: &Board) -> Option<GameResult> {
+    if generate_moves(board).is_empty() {
+        return Some(if board.in_check() { GameResult::win_for(board.side.enemy()) } else { GameResult::Draw });
+    }
+    if board.state().halfmove_clock >= 100 || board.is_repetition(false) || insufficient_material(board) {
+        return Some(GameResult::Draw);
+    }
+    None
+}
+
+// Result of the position without searching it, by the rules or the tablebases
+pub fn adjudicate(search: &Search, board: &Board) -> Option<GameResult> {
+    if let Some(result) = game_over(board) {
+        return Some(result);
+    }
+    match search.probe_syzygy_wdl(board)? {
+        WdlProbeResult::Win => Some(GameResult::win_for(board.side)),
+        WdlProbeResult::Loss => Some(GameResult::win_for(board.side.enemy())),
+        _ => Some(GameResult::Draw),
+    }
+}
+
+// Plays a game from the board after a few random moves, None when the opening is over or
+// one-sided before the game starts
+pub fn play_game(search: &mut Search, board: &mut Board, rng: &mut impl Rng, config: &DatagenConfig) -> Option<Game> {
+    for _ in 0..config.random_plies {
+        let &mov = generate_moves(board).choose(rng)?;
+        board.make_move(mov);
+    }
+    let params = SearchParams { nodes: Some(config.nodes), ..Default::default() };
+    if adjudicate(search, board).is_some() || search.search(params, board).highest_eval.abs() > config.max_opening_score {
+        return None;
+    }
+
+    let mut samples = Vec::new();
+    let mut scores = ScoreTracker::new(config.adjudication);
+    loop {
+        if let Some(result) = adjudicate(search, board) {
+            return Some(Game { samples, result });
+        }
+
+        let result = search.search(params, board);
+        let &best_move = result.pv.first()?;
+        let score = result.highest_eval;
+        let white_score = if board.side == Side::White { score } else { -score };
+        let tactical = board.in_check() || board.is_capture(best_move) || best_move.is_promotion() || best_move.move_type() == MoveType::EnPassant;
+        if !tactical && score.abs() < TB_WIN_THRESHOLD {
+            samples.push(Sample { fen: board.fen(), score: white_score, side: board.side, packed: PackedBoard::new(board, score) });
+        }
+
+        if let Some(result) = scores.update(white_score) {
+            return Some(Game { samples, result });
+        }
+        board.make_move(best_move);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::listener::SilentListener;
+    use rand::SeedableRng;
+
+    #[test]
+    fn packs_boards_for_the_side_to_move() {
+        let board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b KQkq - 0 1");
+        let packed = PackedBoard::new(&board, -35);
+        assert_eq!(PackedBoard::from_bytes(&packed.to_bytes()), packed);
+        assert_eq!(packed.occupancy.count_ones(), board.occupied_squares.count_ones());
+        assert_eq!((packed.score, packed.king_square, packed.opponent_king_square), (-35, 4, 4));
+        // Black's pieces become white's, with black's back rank as rank 1
+        let placement = |board: &Board| board.fen().split(' ').next().unwrap().to_string();
+        assert_eq!(placement(&packed.to_board()), placement(&board.mirrored()));
+
+        let board = Board::from_fen("8/8/8/4k3/8/8/3P4/3K4 w - - 0 1");
+        assert_eq!(placement(&PackedBoard::new(&board, 0).to_board()), placement(&board));
+    }
+
+    #[test]
+    fn adjudicates_by_score() {
+        let adjudication = Adjudication { resign_score: 500, resign_plies: 3, draw_score: 5, draw_plies: 2, draw_min_plies: 4 };
+        let mut scores = ScoreTracker::new(adjudication);
+        assert_eq!([600, 550, 0, -700, -800].map(|score| scores.update(score)), [None; 5]);
+        assert_eq!(scores.update(-900), Some(GameResult::BlackWin));
+
+        let mut scores = ScoreTracker::new(adjudication);
+        assert_eq!([0, 0, 0, 0, 3].map(|score| scores.update(score)), [None; 5]);
+        assert_eq!(scores.update(-5), Some(GameResult::Draw));
+    }
+
+    #[test]
+    fn plays_adjudicated_games() {
+        let mut search = Search { listener: Box::new(SilentListener), ..Default::default() };
+        let config = DatagenConfig { nodes: 500, ..Default::default() };
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(1);
+        let mut games = 0;
+        while games < 2 {
+            let mut board = Board::start_pos();
+            let Some(game) = play_game(&mut search, &mut board, &mut rng, &config) else { continue };
+            assert!(!game.samples.is_empty());
+            let mut text = Vec::new();
+            let mut binary = Vec::new();
+            game.write_text(&mut text).unwrap();
+            game.write_binary(&mut binary).unwrap();
+            assert_eq!(String::from_utf8(text).unwrap().lines().count(), game.samples.len());
+            assert_eq!(binary.len(), game.samples.len() * PACKED_BOARD_SIZE);
+            for (sample, bytes) in game.samples.iter().zip(binary.chunks(PACKED_BOARD_SIZE)) {
+                let packed = PackedBoard::from_bytes(bytes.try_into().unwrap());
+                assert_eq!(packed.result, game.result.relative(sample.side));
+                assert!(!Board::from_fen(&sample.fen).in_check());
+            }
+            games += 1;
+        }
+    }
+
+    #[test]
+    fn adjudicates_finished_games() {
+        let search = Search::default();
+        assert_eq!(adjudicate(&search, &Board::from_fen("4k3/4Q3/4K3/8/8/8/8/8 b - - 0 1")), Some(GameResult::WhiteWin));
+        assert_eq!(adjudicate(&search, &Board::from_fen("K7/8/kq6/8/8/8/8/8 w - - 0 1")), Some(GameResult::Draw));
+        assert_eq!(adjudicate(&search, &Board::from_fen("8/8/8/4k3/8/8/3N4/3K4 w - - 0 1")), Some(GameResult::Draw));
+        assert_eq!(adjudicate(&search, &Board::start_pos()), None);
+        assert_eq!(game_over(&Board::from_fen("8/8/8/4k3/8/8/8/R3K3 b - - 0 1")), None);
+        #[cfg(feature = "syzygy")]
+        assert_eq!(adjudicate(&search, &Board::from_fen("8/8/8/4k3/8/8/8/R3K3 b - - 0 1")), Some(GameResult::WhiteWin));
+    }
+}
@@ -0,0 +1,274 @@
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::board::piece_move::Move;
+use crate::board::{Board, STARTING_FEN};
+use crate::evaluation::eval_params::{EvalParams, DEFAULT_EVAL_PARAMS};
+use crate::evaluation::kpk;
+use crate::evaluation::nnue::Network;
+use crate::search::listener::SearchListener;
+use crate::search::transposition_table::{DEFAULT_HASH_MB, MAX_HASH_MB};
+use crate::search::{Search, SearchParams, SearchResult, StopSignal};
+
+/// Settings that persist between searches, mirroring the UCI options.
+#[derive(Clone, Debug)]
+pub struct EngineOptions {
+    pub own_book: bool,      // Play moves from the opening book when the position is in it
+    pub move_overhead: u128, // Milliseconds subtracted from every move to cover GUI and network latency
+    pub eval_file: String,   // Path of the NNUE network, empty for the hand crafted evaluation
+    pub network: Option<Arc<Network>>,
+    pub eval_params_file: String, // Path of the hand crafted evaluation weights, empty for the built in ones
+    pub eval_params: Arc<EvalParams>,
+    pub hash: usize, // Size of the hash table in MB
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        Self {
+            own_book: true,
+            move_overhead: 10,
+            eval_file: String::new(),
+            network: None,
+            eval_params_file: String::new(),
+            eval_params: DEFAULT_EVAL_PARAMS.clone(),
+            hash: DEFAULT_HASH_MB,
+        }
+    }
+}
+
+impl EngineOptions {
+    // Applies a UCI style option, returning a message describing why it was rejected
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        match name.to_ascii_lowercase().as_str() {
+            "ownbook" => {
+                self.own_book = value.parse().map_err(|_| format!("invalid value for OwnBook: {}", value))?;
+            }
+            "move overhead" => {
+                self.move_overhead = value.parse().map_err(|_| format!("invalid value for Move Overhead: {}", value))?;
+            }
+            "hash" => {
+                self.hash = value.parse().ok().filter(|mb| (1..=MAX_HASH_MB).contains(mb)).ok_or_else(|| format!("invalid value for Hash: {}", value))?;
+            }
+            "evalfile" => {
+                self.network = match value {
+                    "" | "<empty>" => None,
+                    path => Some(Arc::new(Network::load(path)?)),
+                };
+                self.eval_file = value.to_string();
+            }
+            "evalparams" => {
+                self.eval_params = match value {
+                    "" | "<empty>" => DEFAULT_EVAL_PARAMS.clone(),
+                    path => Arc::new(EvalParams::load(path)?),
+                };
+                self.eval_params_file = value.to_string();
+            }
+            _ => return Err(format!("unknown option: {}", name)),
+        }
+        Ok(())
+    }
+}
+
+/// Embeddable chess engine owning the current position and a search that runs on its own thread.
+///
+/// All methods take `&self`, so an `Engine` can be shared between threads, for example to stop a
+/// search from one thread while another one waits for its result. Every engine has its own hash
+/// table, so starting a new game on one doesn't clear the others.
+pub struct Engine {
+    board: Mutex<Board>,
+    options: Mutex<EngineOptions>,
+    search: Arc<Mutex<Search>>,
+    should_quit: Arc<StopSignal>,
+    current_search: Mutex<Option<SearchHandle>>,
+}
+
+impl Engine {
+    pub fn new() -> Self {
+        kpk::initialize();
+        let search = Search::default();
+        let should_quit = search.should_quit.clone();
+        Self {
+            board: Mutex::new(Board::start_pos()),
+            options: Mutex::new(EngineOptions::default()),
+            search: Arc::new(Mutex::new(search)),
+            should_quit,
+            current_search: Mutex::new(None),
+        }
+    }
+
+    // Sets up the position from a FEN string followed by moves in long algebraic notation. The
+    // position is left unchanged if the FEN is malformed or any of the moves is not legal.
+    pub fn set_position(&self, fen: &str, moves: &[&str]) -> Result<(), String> {
+        let mut board = Board::try_from_fen(fen)?;
+        for (index, move_string) in moves.iter().enumerate() {
+            match board.parse_uci_move(move_string).map_err(|error| format!("{} at move {}", error, index + 1))? {
+                Move::NULL => board.make_null_move(),
+                mov => board.make_move(mov),
+            }
+        }
+        *self.board.lock().unwrap() = board;
+        Ok(())
+    }
+    pub fn set_start_position(&self, moves: &[&str]) -> Result<(), String> {
+        self.set_position(STARTING_FEN, moves)
+    }
+    pub fn board(&self) -> Board {
+        self.board.lock().unwrap().clone()
+    }
+
+    // Current position set up with the evaluation selected by the options
+    pub fn evaluation_board(&self) -> Board {
+        let options = self.options();
+        let mut board = self.board();
+        board.set_eval_params(options.eval_params);
+        board.set_network(options.network);
+        board
+    }
+
+    pub fn options(&self) -> EngineOptions {
+        self.options.lock().unwrap().clone()
+    }
+    pub fn set_options(&self, options: EngineOptions) {
+        *self.options.lock().unwrap() = options;
+    }
+    pub fn set_option(&self, name: &str, value: &str) -> Result<(), String> {
+        self.options.lock().unwrap().set(name, value)
+    }
+
+    // Replaces the listener receiving search events, waiting for a running search to finish first
+    pub fn set_listener(&self, listener: Box<dyn SearchListener>) {
+        self.search.lock().unwrap().listener = listener;
+    }
+
+    // Starts searching the current position in the background, stopping any search in progress
+    pub fn go(&self, mut limits: SearchParams) -> SearchHandle {
+        self.stop();
+        self.wait();
+
+        let options = self.options();
+        limits.use_book &= options.own_book;
+        limits.move_overhead = options.move_overhead;
+        let mut board = self.evaluation_board();
+        self.search.lock().unwrap().resize_hash(options.hash);
+        let search = self.search.clone();
+        self.should_quit.reset();
+
+        let thread = thread::spawn(move || search.lock().unwrap().search(limits, &mut board));
+        let handle = SearchHandle {
+            should_quit: self.should_quit.clone(),
+            thread: Arc::new(Mutex::new(SearchThread { handle: Some(thread), result: None })),
+        };
+        *self.current_search.lock().unwrap() = Some(handle.clone());
+        handle
+    }
+    pub fn stop(&self) {
+        self.should_quit.stop();
+    }
+    // Blocks until the current search finishes, returning its result if a search was started
+    pub fn wait(&self) -> Option<SearchResult> {
+        let handle = self.current_search.lock().unwrap().clone();
+        handle.map(|handle| handle.wait())
+    }
+    pub fn is_searching(&self) -> bool {
+        self.current_search.lock().unwrap().as_ref().is_some_and(|handle| !handle.is_finished())
+    }
+
+    // Clears the hash table and move ordering history and resets to the starting position
+    pub fn new_game(&self) {
+        self.stop();
+        self.wait();
+        let mut search = self.search.lock().unwrap();
+        search.resize_hash(self.options().hash);
+        search.new_game();
+        *self.board.lock().unwrap() = Board::start_pos();
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct SearchThread {
+    handle: Option<JoinHandle<SearchResult>>,
+    result: Option<SearchResult>,
+}
+
+/// Handle to a search started with `Engine::go`, which can be cloned and shared between threads.
+#[derive(Clone)]
+pub struct SearchHandle {
+    should_quit: Arc<StopSignal>,
+    thread: Arc<Mutex<SearchThread>>,
+}
+
+impl SearchHandle {
+    pub fn stop(&self) {
+        self.should_quit.stop();
+    }
+    pub fn is_finished(&self) -> bool {
+        let thread = self.thread.lock().unwrap();
+        thread.handle.as_ref().is_none_or(|handle| handle.is_finished())
+    }
+    // Blocks until the search finishes. Panics if the search thread panicked.
+    pub fn wait(&self) -> SearchResult {
+        let mut thread = self.thread.lock().unwrap();
+        if let Some(handle) = thread.handle.take() {
+            match handle.join() {
+                Ok(result) => thread.result = Some(result),
+                Err(panic) => std::panic::resume_unwind(panic),
+            }
+        }
+        thread.result.clone().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::listener::SilentListener;
+    use crate::search::transposition_table::TranspositionTable;
+    use crate::search::SearchMode;
+
+    #[test]
+    fn searches_position_after_moves() {
+        let engine = Engine::new();
+        engine.set_listener(Box::new(SilentListener));
+        engine.set_start_position(&["e2e4", "e7e5"]).unwrap();
+        assert_eq!(engine.set_start_position(&["e2e4", "e2e4"]), Err("illegal move: e2e4 at move 2".to_string()));
+        assert!(engine.set_position("8/8/8/8/8/8/8/8 w - - 0 1", &[]).is_err());
+        assert_eq!(engine.board().fen(), "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2");
+        engine.set_start_position(&["e2e4", "0000", "d2d4"]).unwrap();
+        assert_eq!(engine.board().fen(), "rnbqkbnr/pppppppp/8/8/3PP3/8/PPP2PPP/RNBQKBNR b KQkq d3 0 2");
+
+        let limits = SearchParams {
+            depth: Some(3),
+            search_mode: SearchMode::Infinite,
+            ..Default::default()
+        };
+        let result = engine.go(limits).wait();
+        assert_eq!(result.depth_reached, 3);
+        assert!(!result.pv.is_empty());
+        assert!(!engine.is_searching());
+    }
+
+    #[test]
+    fn resizes_hash_table() {
+        let engine = Engine::new();
+        assert_eq!(engine.set_option("Hash", "0"), Err("invalid value for Hash: 0".to_string()));
+        engine.set_option("Hash", "1").unwrap();
+        engine.new_game();
+        assert_eq!(engine.search.lock().unwrap().transposition_table.entry_count(), TranspositionTable::with_size(1).entry_count());
+    }
+
+    #[test]
+    fn engines_have_separate_hash_tables() {
+        let [engine, other] = [Engine::new(), Engine::new()];
+        engine.set_listener(Box::new(SilentListener));
+        engine.go(SearchParams { depth: Some(3), search_mode: SearchMode::Infinite, use_book: false, ..Default::default() }).wait();
+        other.new_game();
+        let hash = engine.board().zobrist_hash;
+        assert!(engine.search.lock().unwrap().transposition_table.probe(hash).is_some_and(|entry| entry.hash == hash));
+        assert!(other.search.lock().unwrap().transposition_table.probe(hash).is_none());
+    }
+}
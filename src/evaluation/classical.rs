@@ -0,0 +1,281 @@
+use std::cell::RefCell;
+
+use crate::board::bitboard::Bitboard;
+use crate::board::piece::{Piece, PieceType};
+use crate::board::{Board, Side};
+use crate::evaluation::eval_params::EvalParams;
+use crate::evaluation::hash_table::{HashTable, PAWN_TABLE_SIZE};
+use crate::evaluation::material::{probe_material, MaterialEntry};
+use crate::evaluation::score::Score;
+use crate::move_generation::attack_tables::*;
+
+const FILE_A: Bitboard = Bitboard(0x0101010101010101);
+
+fn file_mask(file: usize) -> Bitboard {
+    Bitboard(FILE_A.0 << file)
+}
+fn adjacent_files(file: usize) -> Bitboard {
+    let mut files = Bitboard(0);
+    if file > 0 {
+        files |= file_mask(file - 1);
+    }
+    if file < 7 {
+        files |= file_mask(file + 1);
+    }
+    files
+}
+// All squares on the ranks in front of the square as seen from the side
+fn forward_ranks(side: Side, square: usize) -> Bitboard {
+    let row = square / 8;
+    match side {
+        Side::White => Bitboard((1 << (8 * row)) - 1),
+        Side::Black => Bitboard(u64::MAX.checked_shl(8 * (row as u32 + 1)).unwrap_or(0)),
+    }
+}
+fn relative_rank(side: Side, square: usize) -> usize {
+    match side {
+        Side::White => 7 - square / 8,
+        Side::Black => square / 8,
+    }
+}
+fn up(side: Side, bitboard: Bitboard) -> Bitboard {
+    match side {
+        Side::White => bitboard.north(),
+        Side::Black => bitboard.south(),
+    }
+}
+fn pawn_attacks(side: Side, pawns: Bitboard) -> Bitboard {
+    match side {
+        Side::White => pawns.north_west() | pawns.north_east(),
+        Side::Black => pawns.south_west() | pawns.south_east(),
+    }
+}
+
+/// Positional terms of one side, kept apart so they can be reported one by one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SideTerms {
+    pub pawn_structure: Score,
+    pub passed_pawns: Score,
+    pub mobility: Score,
+    pub king_attack: Score,
+    pub pawn_shield: Score,
+    pub bishop_pair: Score,
+    pub rook_files: Score,
+    pub outposts: Score,
+    pub threats: Score,
+}
+
+impl SideTerms {
+    pub fn named(&self) -> [(&'static str, Score); 9] {
+        [
+            ("Pawn structure", self.pawn_structure),
+            ("Passed pawns", self.passed_pawns),
+            ("Mobility", self.mobility),
+            ("King attack", self.king_attack),
+            ("Pawn shield", self.pawn_shield),
+            ("Bishop pair", self.bishop_pair),
+            ("Rook files", self.rook_files),
+            ("Outposts", self.outposts),
+            ("Threats", self.threats),
+        ]
+    }
+    pub fn total(&self) -> Score {
+        self.named().iter().fold(Score::default(), |total, (_, score)| total + *score)
+    }
+}
+
+/// Pawn structure of both sides, which only depends on where the pawns are.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PawnEntry {
+    pub pawn_structure: [Score; 2],
+    pub passed_pawns: [Score; 2],
+    pub passed: [Bitboard; 2],
+}
+
+thread_local! {
+    static PAWN_TABLE: RefCell<HashTable<PawnEntry>> = RefCell::new(HashTable::new(PAWN_TABLE_SIZE));
+}
+
+/// Positional terms beyond material and piece-square tables, from white's perspective.
+pub fn evaluate_terms(board: &Board) -> Score {
+    let (pawns, material) = (probe_pawns(board), probe_material(board));
+    terms(board, Side::White, &pawns, &material).total() - terms(board, Side::Black, &pawns, &material).total()
+}
+
+pub fn side_terms(board: &Board, side: Side) -> SideTerms {
+    terms(board, side, &probe_pawns(board), &probe_material(board))
+}
+
+fn terms(board: &Board, side: Side, pawn_entry: &PawnEntry, material: &MaterialEntry) -> SideTerms {
+    let params = &board.eval_params;
+    let enemy = side.enemy();
+    let pawns = board.piece_squares[Piece::new(PieceType::Pawn, side)];
+    let enemy_pawns = board.piece_squares[Piece::new(PieceType::Pawn, enemy)];
+    let own_pawn_attacks = pawn_attacks(side, pawns);
+    let enemy_pawn_attacks = pawn_attacks(enemy, enemy_pawns);
+
+    let mut terms = SideTerms { pawn_structure: pawn_entry.pawn_structure[side], passed_pawns: pawn_entry.passed_pawns[side], ..SideTerms::default() };
+    pieces(board, side, own_pawn_attacks, enemy_pawn_attacks, &mut terms);
+    terms.pawn_shield = pawn_shield(board, side, pawns);
+    terms.bishop_pair = material.imbalance[side];
+
+    let enemy_pieces = board.side_squares[enemy] & !enemy_pawns & !board.piece_squares[Piece::new(PieceType::King, enemy)];
+    terms.threats += params.threat_by_pawn * (own_pawn_attacks & enemy_pieces).count_ones() as i32;
+    terms
+}
+
+pub fn pawn_entry(board: &Board) -> PawnEntry {
+    let mut entry = PawnEntry::default();
+    for side in [Side::White, Side::Black] {
+        let pawns = board.piece_squares[Piece::new(PieceType::Pawn, side)];
+        let enemy_pawns = board.piece_squares[Piece::new(PieceType::Pawn, side.enemy())];
+        pawn_structure(&board.eval_params, side, pawns, enemy_pawns, &mut entry);
+    }
+    entry
+}
+
+// Cached by the pawn key of the board
+pub fn probe_pawns(board: &Board) -> PawnEntry {
+    PAWN_TABLE.with_borrow_mut(|table| table.probe(board.pawn_hash, &board.eval_params, || pawn_entry(board)))
+}
+
+fn pawn_structure(params: &EvalParams, side: Side, pawns: Bitboard, enemy_pawns: Bitboard, entry: &mut PawnEntry) {
+    let enemy_pawn_attacks = pawn_attacks(side.enemy(), enemy_pawns);
+    for square in pawns {
+        let file = file_mask(square % 8);
+        let adjacent = adjacent_files(square % 8);
+        let forward = forward_ranks(side, square);
+
+        // Only the rear pawn of a doubled pair is penalized, and only the front one can be passed
+        if pawns & file & forward != 0 {
+            entry.pawn_structure[side] += params.doubled_pawn;
+        } else if enemy_pawns & (file | adjacent) & forward == 0 {
+            entry.passed_pawns[side] += params.passed_pawn[relative_rank(side, square)];
+            entry.passed[side].set_bit(square);
+        }
+
+        if pawns & adjacent == 0 {
+            entry.pawn_structure[side] += params.isolated_pawn;
+        } else if pawns & adjacent & !forward == 0 && enemy_pawn_attacks & up(side, Bitboard::from_square(square)) != 0 {
+            // Every neighbour has advanced past it and it can't move up without being captured
+            entry.pawn_structure[side] += params.backward_pawn;
+        }
+    }
+}
+
+// Mobility, king attacks, outposts, rook files and threats by minor pieces, which all need the
+// attacks of each piece
+fn pieces(board: &Board, side: Side, own_pawn_attacks: Bitboard, enemy_pawn_attacks: Bitboard, terms: &mut SideTerms) {
+    let params = &board.eval_params;
+    let enemy = side.enemy();
+    let pawns = board.piece_squares[Piece::new(PieceType::Pawn, side)];
+    let enemy_pawns = board.piece_squares[Piece::new(PieceType::Pawn, enemy)];
+    let enemy_majors = board.piece_squares[Piece::new(PieceType::Rook, enemy)] | board.piece_squares[Piece::new(PieceType::Queen, enemy)];
+    let mobility_area = !board.side_squares[side] & !enemy_pawn_attacks;
+
+    let enemy_king = board.piece_squares[Piece::new(PieceType::King, enemy)].lsb();
+    let king_zone = get_king_attack_mask(enemy_king) | Bitboard::from_square(enemy_king);
+    let mut king_attackers = 0;
+    let mut king_attack_weight = 0;
+
+    for piece_type in [PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+        for square in board.piece_squares[Piece::new(piece_type, side)] {
+            let attacks = match piece_type {
+                PieceType::Knight => get_knight_attack_mask(square),
+                PieceType::Bishop => bishop_attacks(square, board.occupied_squares),
+                PieceType::Rook => rook_attacks(square, board.occupied_squares),
+                _ => queen_attacks(square, board.occupied_squares),
+            };
+            let mobility = (attacks & mobility_area).count_ones() as i32;
+            terms.mobility += params.mobility[piece_type] * (mobility - params.mobility_center[piece_type]);
+
+            let zone_attacks = (attacks & king_zone).count_ones() as i32;
+            if zone_attacks > 0 {
+                king_attackers += 1;
+                king_attack_weight += params.king_attack_weight[piece_type] * zone_attacks;
+            }
+
+            match piece_type {
+                PieceType::Knight | PieceType::Bishop => {
+                    // Defended by a pawn on the enemy half and out of reach of the enemy pawns
+                    let rank = relative_rank(side, square);
+                    let is_outpost = (3..=5).contains(&rank) && own_pawn_attacks & Bitboard::from_square(square) != 0 && enemy_pawns & adjacent_files(square % 8) & forward_ranks(side, square) == 0;
+                    if is_outpost {
+                        terms.outposts += if piece_type == PieceType::Knight { params.knight_outpost } else { params.bishop_outpost };
+                    }
+                    terms.threats += params.threat_by_minor * (attacks & enemy_majors).count_ones() as i32;
+                }
+                PieceType::Rook => {
+                    let file = file_mask(square % 8);
+                    if pawns & file == 0 {
+                        terms.rook_files += if enemy_pawns & file == 0 { params.rook_open_file } else { params.rook_semi_open_file };
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    terms.king_attack = params.king_attack * (king_attack_weight * params.king_attacker_scale[king_attackers.min(7)] / 100);
+}
+
+fn pawn_shield(board: &Board, side: Side, pawns: Bitboard) -> Score {
+    let params = &board.eval_params;
+    let king = board.piece_squares[Piece::new(PieceType::King, side)];
+    if relative_rank(side, king.lsb()) > 1 {
+        return Score::default();
+    }
+    let front = up(side, king);
+    let shield = front | front.west() | front.east();
+    params.pawn_shield[0] * (pawns & shield).count_ones() as i32 + params.pawn_shield[1] * (pawns & up(side, shield)).count_ones() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Flips the board vertically and swaps the colours of all pieces and the side to move
+    #[test]
+    fn symmetric_position_is_balanced() {
+        assert_eq!(evaluate_terms(&Board::start_pos()), Score::default());
+    }
+
+    #[test]
+    fn mirrored_position_negates_terms() {
+        for fen in ["r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", "r1bq1rk1/pp2nppp/2n1p3/3pP3/1b1P4/2NB1N2/PP3PPP/R1BQK2R w KQ - 0 8"] {
+            let board = Board::from_fen(fen);
+            let mirrored = board.mirrored();
+            assert_eq!(evaluate_terms(&board), -evaluate_terms(&mirrored), "{}", fen);
+        }
+    }
+
+    fn pawn_score(params: &EvalParams, side: Side, pawns: Bitboard, enemy_pawns: Bitboard) -> Score {
+        let mut entry = PawnEntry::default();
+        pawn_structure(params, side, pawns, enemy_pawns, &mut entry);
+        entry.pawn_structure[side] + entry.passed_pawns[side]
+    }
+
+    #[test]
+    fn detects_pawn_structure() {
+        let params = &EvalParams::default();
+        let no_pawns = Bitboard(0);
+        // White passed pawn on e6
+        let passed = Bitboard::from_square(20);
+        assert_eq!(pawn_score(params, Side::White, passed, no_pawns), params.passed_pawn[5] + params.isolated_pawn);
+        // Doubled and isolated pawns on c2 and c3, blocked by a black pawn on c5
+        let doubled = Bitboard::from_square(50) | Bitboard::from_square(42);
+        assert_eq!(pawn_score(params, Side::White, doubled, Bitboard::from_square(26)), params.doubled_pawn + params.isolated_pawn * 2);
+        // Black passed pawn on d2
+        assert_eq!(pawn_score(params, Side::Black, Bitboard::from_square(51), no_pawns), params.passed_pawn[6] + params.isolated_pawn);
+    }
+
+    #[test]
+    fn caches_pawn_structure() {
+        let board = Board::from_fen("8/6p1/8/1P6/8/5p2/7P/k6K w - - 0 1");
+        let entry = probe_pawns(&board);
+        assert_eq!(entry, pawn_entry(&board));
+        assert_eq!(probe_pawns(&board), entry);
+        // b5 for white, f3 for black
+        assert_eq!(entry.passed, [Bitboard::from_square(25), Bitboard::from_square(45)]);
+    }
+}
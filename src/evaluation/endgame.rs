@@ -0,0 +1,310 @@
+use std::sync::LazyLock;
+
+use crate::board::piece::{Piece, PieceType};
+use crate::board::utils::flip_rank;
+use crate::board::{Board, Side};
+use crate::evaluation::kpk;
+use crate::evaluation::piece_square_tables::CENTER_DISTANCE_TABLE;
+
+// Above any material balance but far below the mate scores
+pub const KNOWN_WIN: i32 = 10000;
+
+// Scale factors are out of SCALE_NORMAL, which leaves the evaluation as it is
+pub const SCALE_NORMAL: i32 = 64;
+pub const SCALE_DRAW: i32 = 0;
+const SCALE_OPPOSITE_BISHOPS: i32 = 22;
+const SCALE_OPPOSITE_BISHOPS_WITH_PIECES: i32 = 46;
+
+/// Number of pieces of each type a side has, leaving out the king.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MaterialCounts([u32; 5]);
+
+impl MaterialCounts {
+    pub fn of(board: &Board, side: Side) -> Self {
+        Self(std::array::from_fn(|index| board.piece_squares[Piece::new(PieceType::all()[index], side)].0.count_ones()))
+    }
+    // Pieces of one side in a signature such as "BN", the king is implied
+    fn from_code(code: &str) -> Self {
+        let mut counts = Self::default();
+        for char in code.chars() {
+            let piece_type = match char {
+                'P' => PieceType::Pawn,
+                'N' => PieceType::Knight,
+                'B' => PieceType::Bishop,
+                'R' => PieceType::Rook,
+                'Q' => PieceType::Queen,
+                _ => panic!("invalid piece in material signature: {}", char),
+            };
+            counts.0[piece_type as usize] += 1;
+        }
+        counts
+    }
+    pub fn count(&self, piece_type: PieceType) -> u32 {
+        self.0[piece_type as usize]
+    }
+    pub fn pieces(&self) -> u32 {
+        self.0[1..].iter().sum()
+    }
+}
+
+type EndgameFn = fn(&Board, Side) -> i32;
+
+/// Evaluation of one material signature that replaces the general evaluation, scoring from the
+/// perspective of the stronger side.
+pub struct Endgame {
+    pub name: &'static str,
+    strong: MaterialCounts,
+    weak: MaterialCounts,
+    evaluator: EndgameFn,
+}
+
+impl Endgame {
+    // From the perspective of the side to move
+    pub fn evaluate(&self, board: &Board, strong: Side) -> i32 {
+        let eval = (self.evaluator)(board, strong);
+        if strong == board.side {
+            eval
+        } else {
+            -eval
+        }
+    }
+}
+
+static ENDGAMES: LazyLock<Vec<Endgame>> = LazyLock::new(|| {
+    let endgames: [(&'static str, EndgameFn); 6] = [("KBNK", kbnk), ("KRK", kxk), ("KQK", kxk), ("KPK", kpk), ("KRKP", krkp), ("KQKP", kqkp)];
+    endgames
+        .into_iter()
+        .map(|(name, evaluator)| {
+            let (strong, weak) = name[1..].split_once('K').unwrap();
+            Endgame { name, strong: MaterialCounts::from_code(strong), weak: MaterialCounts::from_code(weak), evaluator }
+        })
+        .collect()
+});
+
+// The specialized evaluator for the material on the board and the side it is evaluated for
+pub fn find_endgame(board: &Board) -> Option<(&'static Endgame, Side)> {
+    // Every known endgame has at most four pieces
+    if board.occupied_squares.0.count_ones() > 4 {
+        return None;
+    }
+    let counts = [MaterialCounts::of(board, Side::White), MaterialCounts::of(board, Side::Black)];
+    for strong in [Side::White, Side::Black] {
+        let weak = strong.enemy();
+        if let Some(endgame) = ENDGAMES.iter().find(|endgame| endgame.strong == counts[strong] && endgame.weak == counts[weak]) {
+            return Some((endgame, strong));
+        }
+    }
+    None
+}
+
+// How much of an evaluation from the side to move's perspective the side ahead can expect to
+// convert, out of SCALE_NORMAL
+pub fn scale_factor(board: &Board, eval: i32) -> i32 {
+    let strong = if eval > 0 { board.side } else { board.side.enemy() };
+    let weak = strong.enemy();
+    let strong_counts = MaterialCounts::of(board, strong);
+    let weak_counts = MaterialCounts::of(board, weak);
+    let params = &board.eval_params;
+
+    if strong_counts.count(PieceType::Pawn) == 0 {
+        if strong_counts.count(PieceType::Knight) == 2 && strong_counts.pieces() == 2 && weak_counts == MaterialCounts::default() {
+            return SCALE_DRAW;
+        }
+        // Without pawns a side needs more than a minor piece beyond what the other side has
+        let strong_material = non_pawn_material(board, strong);
+        let weak_material = non_pawn_material(board, weak);
+        if strong_material - weak_material <= params.piece_value(PieceType::Bishop) {
+            return if strong_material < params.piece_value(PieceType::Rook) {
+                SCALE_DRAW
+            } else if weak_material <= params.piece_value(PieceType::Bishop) {
+                4
+            } else {
+                14
+            };
+        }
+    }
+
+    if strong_counts.count(PieceType::Bishop) == 1 && strong_counts.pieces() == 1 && is_wrong_bishop_draw(board, strong) {
+        return SCALE_DRAW;
+    }
+
+    if strong_counts.count(PieceType::Bishop) == 1 && weak_counts.count(PieceType::Bishop) == 1 {
+        let strong_bishop = board.piece_squares[Piece::new(PieceType::Bishop, strong)].lsb();
+        let weak_bishop = board.piece_squares[Piece::new(PieceType::Bishop, weak)].lsb();
+        if is_light_square(strong_bishop) != is_light_square(weak_bishop) {
+            return if strong_counts.pieces() == 1 && weak_counts.pieces() == 1 { SCALE_OPPOSITE_BISHOPS } else { SCALE_OPPOSITE_BISHOPS_WITH_PIECES };
+        }
+    }
+    SCALE_NORMAL
+}
+
+fn non_pawn_material(board: &Board, side: Side) -> i32 {
+    PieceType::all()[1..5].iter().map(|&piece_type| board.piece_squares[Piece::new(piece_type, side)].0.count_ones() as i32 * board.eval_params.piece_value(piece_type)).sum()
+}
+
+// Rook pawns that promote on a square the bishop doesn't cover, with the defending king in the corner
+fn is_wrong_bishop_draw(board: &Board, strong: Side) -> bool {
+    let pawns = board.piece_squares[Piece::new(PieceType::Pawn, strong)];
+    let file = pawns.lsb() % 8;
+    if pawns.0 == 0 || (file != 0 && file != 7) || pawns.0 & !(0x0101010101010101 << file) != 0 {
+        return false;
+    }
+    let queening_square = match strong {
+        Side::White => file,
+        Side::Black => 56 + file,
+    };
+    let bishop = board.piece_squares[Piece::new(PieceType::Bishop, strong)].lsb();
+    let weak_king = board.piece_squares[Piece::new(PieceType::King, strong.enemy())].lsb();
+    is_light_square(bishop) != is_light_square(queening_square) && distance(weak_king, queening_square) <= 1
+}
+
+fn is_light_square(square: usize) -> bool {
+    (square / 8 + square % 8).is_multiple_of(2)
+}
+
+// Number of king moves between two squares
+fn distance(from: usize, to: usize) -> i32 {
+    let rank_distance = (from / 8).abs_diff(to / 8);
+    let file_distance = (from % 8).abs_diff(to % 8);
+    rank_distance.max(file_distance) as i32
+}
+
+fn push_to_edge(square: usize) -> i32 {
+    CENTER_DISTANCE_TABLE[square] * 20
+}
+fn push_close(from: usize, to: usize) -> i32 {
+    140 - 20 * distance(from, to)
+}
+
+// Square of a piece as if the strong side were white, so pawns of the strong side move towards rank 8
+fn relative_square(board: &Board, strong: Side, piece: Piece) -> usize {
+    let square = board.piece_squares[piece].lsb();
+    match strong {
+        Side::White => square,
+        Side::Black => flip_rank(square),
+    }
+}
+
+fn kings(board: &Board, strong: Side) -> (usize, usize) {
+    (relative_square(board, strong, Piece::new(PieceType::King, strong)), relative_square(board, strong, Piece::new(PieceType::King, strong.enemy())))
+}
+
+// A rook or queen against a lone king, mated by driving the king to the edge
+fn kxk(board: &Board, strong: Side) -> i32 {
+    let (strong_king, weak_king) = kings(board, strong);
+    KNOWN_WIN + non_pawn_material(board, strong) + push_to_edge(weak_king) + push_close(strong_king, weak_king)
+}
+
+// Mate is only possible in the two corners of the bishop's color
+fn kbnk(board: &Board, strong: Side) -> i32 {
+    let (strong_king, weak_king) = kings(board, strong);
+    let bishop = relative_square(board, strong, Piece::new(PieceType::Bishop, strong));
+    // a8 and h1 are light, h8 and a1 dark
+    let corners = if is_light_square(bishop) { [0, 63] } else { [7, 56] };
+    let corner_distance = corners.map(|corner| (weak_king / 8).abs_diff(corner / 8) + (weak_king % 8).abs_diff(corner % 8)).into_iter().min().unwrap() as i32;
+    KNOWN_WIN + non_pawn_material(board, strong) + (14 - corner_distance) * 40 + push_close(strong_king, weak_king)
+}
+
+// Exact from the bitbase, won positions still prefer the pawn further up the board
+fn kpk(board: &Board, strong: Side) -> i32 {
+    let (strong_king, weak_king) = kings(board, strong);
+    let pawn = relative_square(board, strong, Piece::new(PieceType::Pawn, strong));
+    if !kpk::probe(board.side == strong, strong_king, weak_king, pawn) {
+        return 0;
+    }
+    KNOWN_WIN + board.eval_params.piece_value(PieceType::Pawn) + 7 - (pawn / 8) as i32
+}
+
+// Rook against a pawn supported by its king, following the distances of both kings to the pawn
+fn krkp(board: &Board, strong: Side) -> i32 {
+    let (strong_king, weak_king) = kings(board, strong);
+    let rook = relative_square(board, strong, Piece::new(PieceType::Rook, strong));
+    let pawn = relative_square(board, strong, Piece::new(PieceType::Pawn, strong.enemy()));
+    let queening_square = 56 + pawn % 8;
+    let rook_value = board.eval_params.piece_value(PieceType::Rook);
+    let strong_to_move = board.side == strong;
+    let rank = |square: usize| 7 - (square / 8) as i32;
+
+    if strong_king % 8 == pawn % 8 && strong_king > pawn {
+        // The king already stands in front of the pawn
+        rook_value - distance(strong_king, pawn)
+    } else if distance(weak_king, pawn) >= 3 + !strong_to_move as i32 && distance(weak_king, rook) >= 3 {
+        // The defending king is too far away to help its pawn
+        rook_value - distance(strong_king, pawn)
+    } else if rank(weak_king) <= 2 && distance(weak_king, pawn) == 1 && rank(strong_king) >= 3 && distance(strong_king, pawn) > 2 + strong_to_move as i32 {
+        // Pawn and king are far advanced and the attacking king is cut off
+        80 - 8 * distance(strong_king, pawn)
+    } else {
+        let in_front = pawn + 8;
+        200 - 8 * (distance(strong_king, in_front) - distance(weak_king, in_front) - distance(pawn, queening_square))
+    }
+}
+
+// Queen against a pawn on its seventh rank is a draw on the rook and bishop files when the
+// defending king is next to it
+fn kqkp(board: &Board, strong: Side) -> i32 {
+    let (strong_king, weak_king) = kings(board, strong);
+    let pawn = relative_square(board, strong, Piece::new(PieceType::Pawn, strong.enemy()));
+    let params = &board.eval_params;
+    let mut eval = push_close(strong_king, weak_king);
+    if pawn / 8 != 6 || distance(weak_king, pawn) != 1 || ![0, 2, 5, 7].contains(&(pawn % 8)) {
+        eval += params.piece_value(PieceType::Queen) - params.piece_value(PieceType::Pawn);
+    }
+    eval
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::evaluate;
+
+    fn name(fen: &str) -> Option<&'static str> {
+        find_endgame(&Board::from_fen(fen)).map(|(endgame, _)| endgame.name)
+    }
+
+    #[test]
+    fn selects_by_material_signature() {
+        assert_eq!(name("8/8/8/4k3/8/8/2B5/1N2K3 w - - 0 1"), Some("KBNK"));
+        assert_eq!(name("8/8/8/4k3/8/8/2r5/4K3 w - - 0 1"), Some("KRK"));
+        assert_eq!(name("8/8/8/4k3/8/8/2p5/4KQ2 w - - 0 1"), Some("KQKP"));
+        assert_eq!(name("8/8/8/4k3/8/8/2pp4/4KQ2 w - - 0 1"), None);
+        assert_eq!(find_endgame(&Board::from_fen("8/8/8/4k3/8/8/2r5/4K3 w - - 0 1")).unwrap().1, Side::Black);
+    }
+
+    #[test]
+    fn drives_bishop_and_knight_to_the_right_corner() {
+        // Light squared bishop, so a8 and h1 are the mating corners
+        let right_corner = evaluate(&Board::from_fen("k7/8/1K6/8/8/8/8/3BN3 w - - 0 1"));
+        let wrong_corner = evaluate(&Board::from_fen("8/8/8/8/8/1K6/8/k2BN3 w - - 0 1"));
+        assert!(right_corner > wrong_corner);
+        assert!(wrong_corner > KNOWN_WIN);
+        assert_eq!(evaluate(&Board::from_fen("k7/8/1K6/8/8/8/8/3BN3 b - - 0 1")), -right_corner);
+    }
+
+    #[test]
+    fn evaluates_pawn_endgames() {
+        // Outside the square of the pawn
+        assert!(evaluate(&Board::from_fen("7k/8/8/8/8/8/P7/K7 w - - 0 1")) > KNOWN_WIN);
+        // The defending king blocks the pawn
+        assert_eq!(evaluate(&Board::from_fen("8/8/4k3/8/4P3/4K3/8/8 b - - 0 1")), 0);
+        // Queen against a bishop pawn on the seventh rank next to its king
+        let drawish = evaluate(&Board::from_fen("8/8/8/8/8/1Q6/2pk4/K7 w - - 0 1"));
+        let winning = evaluate(&Board::from_fen("8/8/8/8/8/1Q6/3pk3/K7 w - - 0 1"));
+        assert!(winning > drawish + 500);
+    }
+
+    #[test]
+    fn scales_drawish_material() {
+        let knights = Board::from_fen("8/8/8/4k3/8/8/8/1N2KN2 w - - 0 1");
+        assert_eq!(evaluate(&knights), 0);
+        let lone_bishop = Board::from_fen("8/8/8/4k3/8/8/8/2B1K3 w - - 0 1");
+        assert_eq!(evaluate(&lone_bishop), 0);
+        // Light squared bishop that can't cover h8
+        let wrong_bishop = Board::from_fen("7k/8/7P/8/8/8/8/3BK3 w - - 0 1");
+        assert_eq!(scale_factor(&wrong_bishop, 100), SCALE_DRAW);
+        let right_bishop = Board::from_fen("7k/8/7P/8/8/8/8/2B1K3 w - - 0 1");
+        assert_eq!(scale_factor(&right_bishop, 100), SCALE_NORMAL);
+        let opposite_bishops = Board::from_fen("8/5k2/4b3/5p2/5P2/2B2K2/6P1/8 w - - 0 1");
+        assert_eq!(scale_factor(&opposite_bishops, 100), SCALE_OPPOSITE_BISHOPS);
+    }
+}
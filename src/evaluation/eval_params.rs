@@ -0,0 +1,241 @@
+use std::sync::{Arc, LazyLock};
+
+use crate::board::piece::{Piece, PieceType};
+use crate::board::utils::flip_rank;
+use crate::board::Side;
+use crate::evaluation::piece_square_tables::{ENDGAME_PIECE_SQUARE_TABLES, MIDGAME_PIECE_SQUARE_TABLES};
+use crate::evaluation::score::{s, Score};
+
+// Shared by every board that hasn't been given other parameters, so creating a board doesn't
+// allocate a new copy of the tables
+pub static DEFAULT_EVAL_PARAMS: LazyLock<Arc<EvalParams>> = LazyLock::new(|| Arc::new(EvalParams::default()));
+
+/// A group of weights that can be flattened to a list of integers, for saving and tuning.
+pub trait Weights {
+    fn count(&self) -> usize;
+    fn write(&self, values: &mut Vec<i32>);
+    // Reads exactly `count` values from the front of the slice
+    fn read(&mut self, values: &[i32]);
+}
+
+impl Weights for i32 {
+    fn count(&self) -> usize {
+        1
+    }
+    fn write(&self, values: &mut Vec<i32>) {
+        values.push(*self);
+    }
+    fn read(&mut self, values: &[i32]) {
+        *self = values[0];
+    }
+}
+
+impl Weights for Score {
+    fn count(&self) -> usize {
+        2
+    }
+    fn write(&self, values: &mut Vec<i32>) {
+        values.extend([self.mg(), self.eg()]);
+    }
+    fn read(&mut self, values: &[i32]) {
+        *self = s(values[0], values[1]);
+    }
+}
+
+impl<T: Weights, const N: usize> Weights for [T; N] {
+    fn count(&self) -> usize {
+        self.iter().map(|weights| weights.count()).sum()
+    }
+    fn write(&self, values: &mut Vec<i32>) {
+        self.iter().for_each(|weights| weights.write(values));
+    }
+    fn read(&mut self, mut values: &[i32]) {
+        for weights in self.iter_mut() {
+            let len = weights.count();
+            weights.read(&values[..len]);
+            values = &values[len..];
+        }
+    }
+}
+
+// Declares every parameter once, generating the struct, its defaults and access to all fields by name
+macro_rules! eval_params {
+    ($($name:ident: $type:ty = $default:expr,)*) => {
+        /// Every weight used by the hand crafted evaluation.
+        #[derive(Clone, Debug, PartialEq)]
+        pub struct EvalParams {
+            $(pub $name: $type,)*
+        }
+
+        impl Default for EvalParams {
+            fn default() -> Self {
+                Self { $($name: $default,)* }
+            }
+        }
+
+        impl EvalParams {
+            pub fn fields(&self) -> Vec<(&'static str, &dyn Weights)> {
+                vec![$((stringify!($name), &self.$name as &dyn Weights)),*]
+            }
+            pub fn fields_mut(&mut self) -> Vec<(&'static str, &mut dyn Weights)> {
+                vec![$((stringify!($name), &mut self.$name as &mut dyn Weights)),*]
+            }
+        }
+    };
+}
+
+eval_params! {
+    // Indexed by piece type, both sides always have a king so it has no value
+    piece_values: [i32; 6] = [100, 320, 330, 500, 900, 0],
+    // Indexed by piece type and square from white's perspective, starting at a8
+    midgame_pst: [[i32; 64]; 6] = MIDGAME_PIECE_SQUARE_TABLES,
+    endgame_pst: [[i32; 64]; 6] = ENDGAME_PIECE_SQUARE_TABLES,
+    // Drives the enemy king to the corner and our king towards it once material comes off
+    king_corner_weight: i32 = 3,
+    // Bonus for the side to move
+    tempo: Score = s(15, 5),
+
+    doubled_pawn: Score = s(-10, -20),
+    isolated_pawn: Score = s(-10, -15),
+    backward_pawn: Score = s(-8, -10),
+    // Indexed by relative rank, where rank 1 is the side's own back rank
+    passed_pawn: [Score; 8] = [s(0, 0), s(5, 10), s(5, 15), s(12, 25), s(25, 45), s(45, 80), s(70, 120), s(0, 0)],
+
+    // Bonus per reachable square above or below the typical count for the piece type
+    mobility: [Score; 6] = [s(0, 0), s(4, 4), s(5, 5), s(3, 5), s(1, 3), s(0, 0)],
+    mobility_center: [i32; 6] = [0, 4, 6, 7, 13, 0],
+
+    king_attack_weight: [i32; 6] = [0, 2, 2, 3, 5, 0],
+    // Percentage of the attack weight that counts, since a lone attacker is rarely dangerous
+    king_attacker_scale: [i32; 8] = [0, 0, 50, 75, 88, 94, 97, 99],
+    king_attack: Score = s(6, 1),
+    // Pawns one and two ranks in front of a king that is still on its first two ranks
+    pawn_shield: [Score; 2] = [s(12, 0), s(6, 0)],
+
+    bishop_pair: Score = s(30, 50),
+    rook_open_file: Score = s(25, 10),
+    rook_semi_open_file: Score = s(12, 8),
+    knight_outpost: Score = s(20, 12),
+    bishop_outpost: Score = s(12, 6),
+    threat_by_pawn: Score = s(45, 35),
+    threat_by_minor: Score = s(25, 30),
+}
+
+impl EvalParams {
+    pub fn midgame_value(&self, piece: Piece, square: usize) -> i32 {
+        self.midgame_pst[piece.piece_type()][Self::relative_square(piece.side(), square)]
+    }
+    pub fn endgame_value(&self, piece: Piece, square: usize) -> i32 {
+        self.endgame_pst[piece.piece_type()][Self::relative_square(piece.side(), square)]
+    }
+    // Material and piece-square value of a piece, tapered together by the evaluation
+    pub fn piece_square_score(&self, piece: Piece, square: usize) -> Score {
+        let value = self.piece_value(piece.piece_type());
+        s(value + self.midgame_value(piece, square), value + self.endgame_value(piece, square))
+    }
+    pub fn piece_value(&self, piece_type: PieceType) -> i32 {
+        self.piece_values[piece_type]
+    }
+    fn relative_square(side: Side, square: usize) -> usize {
+        match side {
+            Side::White => square,
+            Side::Black => flip_rank(square),
+        }
+    }
+
+    // All weights in declaration order
+    pub fn to_vector(&self) -> Vec<i32> {
+        let mut values = Vec::new();
+        self.fields().iter().for_each(|(_, weights)| weights.write(&mut values));
+        values
+    }
+    pub fn set_vector(&mut self, mut values: &[i32]) {
+        for (_, weights) in self.fields_mut() {
+            let len = weights.count();
+            weights.read(&values[..len]);
+            values = &values[len..];
+        }
+    }
+
+    // One parameter per line, its name followed by its values. Piece-square tables are written a
+    // rank per line to stay readable.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (name, weights) in self.fields() {
+            let mut values = Vec::new();
+            weights.write(&mut values);
+            let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+            if values.len() > 16 {
+                text.push_str(name);
+                text.push('\n');
+                for rank in values.chunks(8) {
+                    text.push_str(&format!("    {}\n", rank.join(" ")));
+                }
+            } else {
+                text.push_str(&format!("{} {}\n", name, values.join(" ")));
+            }
+        }
+        text
+    }
+    // Parameters missing from the text keep their default values. Lines starting with # are ignored.
+    pub fn from_text(text: &str) -> Result<Self, String> {
+        let mut params = Self::default();
+        let mut fields = params.fields_mut();
+
+        let mut tokens = text.lines().filter(|line| !line.trim_start().starts_with('#')).flat_map(|line| line.split_whitespace()).peekable();
+        while let Some(name) = tokens.next() {
+            let mut values = Vec::new();
+            while let Some(value) = tokens.peek().and_then(|token| token.parse::<i32>().ok()) {
+                values.push(value);
+                tokens.next();
+            }
+            let (_, weights) = fields.iter_mut().find(|(field, _)| *field == name).ok_or_else(|| format!("unknown parameter: {}", name))?;
+            if values.len() != weights.count() {
+                return Err(format!("expected {} values for {} but found {}", weights.count(), name, values.len()));
+            }
+            weights.read(&values);
+        }
+        Ok(params)
+    }
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|error| format!("could not read parameters {}: {}", path, error))?;
+        Self::from_text(&text)
+    }
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        std::fs::write(path, self.to_text()).map_err(|error| format!("could not write parameters {}: {}", path, error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_round_trip() {
+        let mut params = EvalParams { bishop_pair: s(11, 22), ..Default::default() };
+        params.midgame_pst[PieceType::Knight][27] = 99;
+        assert_eq!(EvalParams::from_text(&params.to_text()), Ok(params));
+    }
+
+    #[test]
+    fn reads_partial_text() {
+        let params = EvalParams::from_text("# only the rook terms\nrook_open_file 1 2\nrook_semi_open_file 3\n    4\n").unwrap();
+        assert_eq!(params.rook_open_file, s(1, 2));
+        assert_eq!(params.rook_semi_open_file, s(3, 4));
+        assert_eq!(params.bishop_pair, EvalParams::default().bishop_pair);
+
+        assert!(EvalParams::from_text("bishop_pair 1").is_err());
+        assert!(EvalParams::from_text("queen_pair 1 2").is_err());
+    }
+
+    #[test]
+    fn vector_round_trip() {
+        let params = EvalParams::default();
+        let mut values = params.to_vector();
+        values[0] = 90;
+        let mut changed = EvalParams::default();
+        changed.set_vector(&values);
+        assert_eq!(changed.piece_values[PieceType::Pawn], 90);
+        assert_eq!(changed.to_vector(), values);
+    }
+}
@@ -1,28 +1,35 @@
 use crate::board::piece::{Piece, PieceType};
-use crate::board::{Board, Side, TOTAL_MATERIAL_STARTPOS};
+use crate::board::Board;
+use crate::evaluation::classical::evaluate_terms;
+use crate::evaluation::endgame::{scale_factor, SCALE_NORMAL};
+use crate::evaluation::material::probe_material;
 use crate::evaluation::piece_square_tables::*;
-
-//const TOTAL_PHASE: i32 = 16 * PieceType::Pawn.phase() + 4 * PieceType::Knight.phase() + 4 * PieceType::Bishop.phase() + 4 * PieceType::Rook.phase() + 2 * PieceType::Queen.phase();
+use crate::evaluation::score::MAX_PHASE;
 
 pub fn evaluate(board: &Board) -> i32 {
-    //let phase = phase(board);
-    //let phase = 0.5;
-    let phase = (board.total_material as f32) / (TOTAL_MATERIAL_STARTPOS as f32);
-    let mut eval = (board.material_balance * board.side.factor()) as f32;
-    eval += ((board.midgame_position_balance * board.side.factor()) as f32) * phase;
-    eval += ((board.endgame_position_balance * board.side.factor()) as f32) * (1.0 - phase);
-    eval += corner_king_evaluation(board) as f32 * (1.0 - phase)*(1.0 - phase) * 3.0;
-    eval as i32
+    let material = probe_material(board);
+    if let Some((endgame, strong)) = material.endgame {
+        return endgame.evaluate(board, strong);
+    }
+    let eval = match &board.accumulator {
+        Some(accumulator) => accumulator.evaluate(board.side),
+        None => classical_evaluation(board, material.phase),
+    };
+    eval * scale_factor(board, eval) / SCALE_NORMAL
+}
+fn classical_evaluation(board: &Board, phase: i32) -> i32 {
+    let score = (board.score + evaluate_terms(board)) * board.side.factor() + board.eval_params.tempo;
+    let mut eval = score.taper(phase);
+    // Only matters in the endgame, so scaled by the square of the endgame phase
+    let endgame_phase = MAX_PHASE - phase;
+    eval += corner_king_evaluation(board) * endgame_phase * endgame_phase * board.eval_params.king_corner_weight / (MAX_PHASE * MAX_PHASE);
+    eval
+}
+// Game phase from MAX_PHASE in the opening down to 0 with only pawns and kings left
+pub fn phase(board: &Board) -> i32 {
+    board.phase.min(MAX_PHASE)
 }
-//fn phase(board: &Board) -> f32 {
-//    let mut phase = 0;
-//    for piece_type in [PieceType::Pawn, PieceType::Knight, PieceType::Rook, PieceType::Queen] {
-//        phase += piece_type.phase() * board.piece_squares[Piece::new(piece_type, Side::White)].count_ones() as i32;
-//        phase += piece_type.phase() * board.piece_squares[Piece::new(piece_type, Side::Black)].count_ones() as i32;
-//    }
-//    (phase as f32) / (TOTAL_PHASE as f32)
-//}
-fn corner_king_evaluation(board: &Board) -> i32 {
+pub(crate) fn corner_king_evaluation(board: &Board) -> i32 {
     let friendly_king_square = board.piece_squares[Piece::new(PieceType::King, board.side)].lsb();
     let enemy_king_square = board.piece_squares[Piece::new(PieceType::King, board.side.enemy())].lsb();
     let enemy_king_center_distance = CENTER_DISTANCE_TABLE[enemy_king_square];
@@ -40,3 +47,22 @@ fn corner_king_evaluation(board: &Board) -> i32 {
     eval += 14 - king_distance;
     eval
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::random_games;
+
+    #[test]
+    fn mirrored_positions_evaluate_the_same() {
+        random_games(1, 100, None, |board| {
+            let mirrored = board.mirrored();
+            assert_eq!(evaluate(board), evaluate(&mirrored), "{} and {}", board.fen(), mirrored.fen());
+        });
+        // Specialized endgames and scaled material
+        for fen in ["8/8/8/4k3/8/8/8/3BNK2 b - - 0 1", "8/8/4k3/8/4P3/4K3/8/8 w - - 0 1", "8/8/8/8/8/1Q6/2pk4/K7 w - - 0 1", "8/5k2/4b3/5p2/5P2/2B2K2/6P1/8 w - - 0 1"] {
+            let board = Board::from_fen(fen);
+            assert_eq!(evaluate(&board), evaluate(&board.mirrored()), "{}", fen);
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use crate::evaluation::eval_params::EvalParams;
+
+pub const PAWN_TABLE_SIZE: usize = 16384;
+pub const MATERIAL_TABLE_SIZE: usize = 4096;
+
+/// Cache for the parts of the evaluation that only depend on some of the pieces, indexed by a
+/// zobrist key of those pieces. Each search thread keeps its own, so no locking is needed.
+pub struct HashTable<T> {
+    entries: Vec<Option<(u64, T)>>,
+    // Entries are only valid for the parameters they were computed with
+    params: Option<Arc<EvalParams>>,
+}
+
+impl<T: Copy> HashTable<T> {
+    pub fn new(size: usize) -> Self {
+        Self { entries: vec![None; size], params: None }
+    }
+
+    // Returns the cached value for the key, computing and storing it on a miss
+    pub fn probe(&mut self, key: u64, params: &Arc<EvalParams>, compute: impl FnOnce() -> T) -> T {
+        if !self.params.as_ref().is_some_and(|table_params| Arc::ptr_eq(table_params, params)) {
+            self.entries.fill(None);
+            self.params = Some(params.clone());
+        }
+        let index = (key % self.entries.len() as u64) as usize;
+        match self.entries[index] {
+            Some((entry_key, value)) if entry_key == key => value,
+            _ => {
+                let value = compute();
+                self.entries[index] = Some((key, value));
+                value
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluation::eval_params::DEFAULT_EVAL_PARAMS;
+
+    #[test]
+    fn caches_by_key_and_params() {
+        let mut table = HashTable::new(16);
+        let params = DEFAULT_EVAL_PARAMS.clone();
+        assert_eq!(table.probe(3, &params, || 1), 1);
+        assert_eq!(table.probe(3, &params, || 2), 1);
+        // Same index, different key
+        assert_eq!(table.probe(19, &params, || 3), 3);
+        assert_eq!(table.probe(3, &params, || 4), 4);
+
+        let other_params = Arc::new(EvalParams::default());
+        assert_eq!(table.probe(3, &other_params, || 5), 5);
+    }
+}
@@ -0,0 +1,207 @@
+use std::sync::LazyLock;
+
+use crate::board::bitboard::Bitboard;
+use crate::board::piece::{Piece, PieceType};
+use crate::board::utils::flip_rank;
+use crate::board::{Board, Side};
+use crate::move_generation::attack_tables::get_king_attack_mask;
+
+// Side to move, both kings and the pawn on files a to d and ranks 2 to 7
+const POSITIONS: usize = 2 * 64 * 64 * 4 * 6;
+
+// Bit flags, so the outcomes of all moves can be combined with a single or
+const INVALID: u8 = 0;
+const UNKNOWN: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 4;
+
+/// Win or draw of every king and pawn against king position, with the pawn's side as white.
+static BITBASE: LazyLock<Vec<u64>> = LazyLock::new(generate);
+
+// Squares are as if the side with the pawn were white, the pawn is on files a to d
+fn index(white_to_move: bool, strong_king: usize, weak_king: usize, pawn: usize) -> usize {
+    let pawn_index = (pawn / 8 - 1) * 4 + pawn % 8;
+    ((pawn_index * 64 + strong_king) * 64 + weak_king) * 2 + white_to_move as usize
+}
+
+fn distance(from: usize, to: usize) -> u32 {
+    (from / 8).abs_diff(to / 8).max((from % 8).abs_diff(to % 8)) as u32
+}
+
+fn pawn_attacks(pawn: usize) -> Bitboard {
+    let pawn = Bitboard::from_square(pawn);
+    pawn.north_west() | pawn.north_east()
+}
+
+// Outcome that follows from the rules alone, before looking at any moves
+fn initial_result(white_to_move: bool, strong_king: usize, weak_king: usize, pawn: usize) -> u8 {
+    let push = pawn - 8;
+    if distance(strong_king, weak_king) <= 1 || strong_king == pawn || weak_king == pawn || (white_to_move && pawn_attacks(pawn).bit(weak_king) != 0) {
+        INVALID
+    } else if white_to_move && pawn / 8 == 1 && strong_king != push && weak_king != push && (distance(weak_king, push) > 1 || distance(strong_king, push) == 1) {
+        // Promotes without the new queen being captured
+        WIN
+    } else if !white_to_move {
+        let escapes = get_king_attack_mask(weak_king) & !(get_king_attack_mask(strong_king) | pawn_attacks(pawn));
+        let pawn_hangs = (get_king_attack_mask(weak_king) & !get_king_attack_mask(strong_king)).bit(pawn) != 0;
+        if escapes.0 == 0 || pawn_hangs {
+            // Stalemate or the pawn is lost
+            DRAW
+        } else {
+            UNKNOWN
+        }
+    } else {
+        UNKNOWN
+    }
+}
+
+// Combines the outcomes of all moves, a position is won for white as soon as one move wins and
+// drawn for black as soon as one move draws
+fn classify(results: &[u8], white_to_move: bool, strong_king: usize, weak_king: usize, pawn: usize) -> u8 {
+    let mut outcomes = INVALID;
+    if white_to_move {
+        for square in get_king_attack_mask(strong_king) {
+            outcomes |= results[index(false, square, weak_king, pawn)];
+        }
+        let push = pawn - 8;
+        if pawn / 8 > 1 {
+            outcomes |= results[index(false, strong_king, weak_king, push)];
+        }
+        if pawn / 8 == 6 && push != strong_king && push != weak_king {
+            outcomes |= results[index(false, strong_king, weak_king, push - 8)];
+        }
+    } else {
+        for square in get_king_attack_mask(weak_king) {
+            outcomes |= results[index(true, strong_king, square, pawn)];
+        }
+    }
+
+    let (good, bad) = if white_to_move { (WIN, DRAW) } else { (DRAW, WIN) };
+    if outcomes & good != 0 {
+        good
+    } else if outcomes & UNKNOWN != 0 {
+        UNKNOWN
+    } else {
+        bad
+    }
+}
+
+// Retrograde analysis: positions are resolved from the ones already known until nothing changes,
+// whatever is still unknown after that can't be won
+fn generate() -> Vec<u64> {
+    let mut results = vec![UNKNOWN; POSITIONS];
+    let mut positions = Vec::with_capacity(POSITIONS);
+    for pawn in (8..56).filter(|square| square % 8 < 4) {
+        for strong_king in 0..64 {
+            for weak_king in 0..64 {
+                for white_to_move in [false, true] {
+                    let index = index(white_to_move, strong_king, weak_king, pawn);
+                    results[index] = initial_result(white_to_move, strong_king, weak_king, pawn);
+                    if results[index] == UNKNOWN {
+                        positions.push((white_to_move, strong_king, weak_king, pawn));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &(white_to_move, strong_king, weak_king, pawn) in positions.iter() {
+            let index = index(white_to_move, strong_king, weak_king, pawn);
+            if results[index] == UNKNOWN {
+                results[index] = classify(&results, white_to_move, strong_king, weak_king, pawn);
+                changed |= results[index] != UNKNOWN;
+            }
+        }
+    }
+
+    let mut bitbase = vec![0; POSITIONS / 64];
+    for (index, &result) in results.iter().enumerate() {
+        if result == WIN {
+            bitbase[index / 64] |= 1 << (index % 64);
+        }
+    }
+    bitbase
+}
+
+// Takes a few milliseconds, so it is better done before the first search than during one
+pub fn initialize() {
+    LazyLock::force(&BITBASE);
+}
+
+// Squares are as if the side with the pawn were white
+pub fn probe(white_to_move: bool, strong_king: usize, weak_king: usize, pawn: usize) -> bool {
+    // Positions are symmetric along the middle of the board
+    let mirror = if pawn % 8 > 3 { 7 } else { 0 };
+    let index = index(white_to_move, strong_king ^ mirror, weak_king ^ mirror, pawn ^ mirror);
+    BITBASE[index / 64] & (1 << (index % 64)) != 0
+}
+
+// Whether the side with the pawn wins, None unless only two kings and a pawn are left
+pub fn probe_board(board: &Board) -> Option<bool> {
+    if board.occupied_squares.0.count_ones() != 3 {
+        return None;
+    }
+    let strong = [Side::White, Side::Black].into_iter().find(|&side| board.piece_squares[Piece::new(PieceType::Pawn, side)].0 != 0)?;
+    let relative_square = |piece: Piece| {
+        let square = board.piece_squares[piece].lsb();
+        match strong {
+            Side::White => square,
+            Side::Black => flip_rank(square),
+        }
+    };
+    Some(probe(
+        board.side == strong,
+        relative_square(Piece::new(PieceType::King, strong)),
+        relative_square(Piece::new(PieceType::King, strong.enemy())),
+        relative_square(Piece::new(PieceType::Pawn, strong)),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn knows_basic_positions() {
+        // Opposition in front of the pawn
+        assert_eq!(probe_board(&Board::from_fen("8/8/4k3/8/4P3/4K3/8/8 b - - 0 1")), Some(false));
+        assert_eq!(probe_board(&Board::from_fen("8/8/4k3/8/4K3/4P3/8/8 b - - 0 1")), Some(true));
+        assert_eq!(probe_board(&Board::from_fen("8/8/4k3/4p3/8/4K3/8/8 w - - 0 1")), Some(false));
+        // Rook pawn with the defending king in the corner
+        assert_eq!(probe_board(&Board::from_fen("7k/8/5K1P/8/8/8/8/8 w - - 0 1")), Some(false));
+        assert_eq!(probe_board(&Board::from_fen("8/8/8/4k3/8/8/2PP4/4K3 w - - 0 1")), None);
+    }
+
+    #[cfg(feature = "syzygy")]
+    #[test]
+    fn matches_syzygy_tables() {
+        use crate::search::search::SYZYGY;
+        use pyrrhic_rs::WdlProbeResult;
+
+        let mut checked = 0;
+        for pawn in 8..56 {
+            let mirror = if pawn % 8 > 3 { 7 } else { 0 };
+            for strong_king in 0..64 {
+                for weak_king in 0..64 {
+                    for white_to_move in [false, true] {
+                        if initial_result(white_to_move, strong_king ^ mirror, weak_king ^ mirror, pawn ^ mirror) == INVALID {
+                            continue;
+                        }
+                        // The tables number squares from a1
+                        let bit = |square: usize| 1u64 << flip_rank(square);
+                        let wdl = SYZYGY
+                            .probe_wdl(bit(strong_king) | bit(pawn), bit(weak_king), bit(strong_king) | bit(weak_king), 0, 0, 0, 0, bit(pawn), 0, white_to_move)
+                            .expect("KPvK tables are missing");
+                        let win = if white_to_move { WdlProbeResult::Win } else { WdlProbeResult::Loss };
+                        assert_eq!(probe(white_to_move, strong_king, weak_king, pawn), wdl == win, "kings {} {}, pawn {}, white to move {}", strong_king, weak_king, pawn, white_to_move);
+                        checked += 1;
+                    }
+                }
+            }
+        }
+        assert!(checked > 300000);
+    }
+}
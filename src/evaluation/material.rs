@@ -0,0 +1,56 @@
+use std::cell::RefCell;
+
+use crate::board::piece::{Piece, PieceType};
+use crate::board::{Board, Side};
+use crate::evaluation::endgame::{find_endgame, Endgame};
+use crate::evaluation::evaluation::phase;
+use crate::evaluation::hash_table::{HashTable, MATERIAL_TABLE_SIZE};
+use crate::evaluation::score::Score;
+
+thread_local! {
+    static MATERIAL_TABLE: RefCell<HashTable<MaterialEntry>> = RefCell::new(HashTable::new(MATERIAL_TABLE_SIZE));
+}
+
+/// Everything the evaluation derives from the number of pieces of each kind alone.
+#[derive(Clone, Copy)]
+pub struct MaterialEntry {
+    pub phase: i32,
+    // Terms of each side that only depend on the material, so far the bishop pair
+    pub imbalance: [Score; 2],
+    // Specialized evaluator and the side it evaluates for
+    pub endgame: Option<(&'static Endgame, Side)>,
+}
+
+pub fn material_entry(board: &Board) -> MaterialEntry {
+    let imbalance = [Side::White, Side::Black].map(|side| {
+        if board.piece_squares[Piece::new(PieceType::Bishop, side)].count_ones() >= 2 {
+            board.eval_params.bishop_pair
+        } else {
+            Score::default()
+        }
+    });
+    MaterialEntry { phase: phase(board), imbalance, endgame: find_endgame(board) }
+}
+
+// Cached by the material key of the board
+pub fn probe_material(board: &Board) -> MaterialEntry {
+    MATERIAL_TABLE.with_borrow_mut(|table| table.probe(board.material_hash, &board.eval_params, || material_entry(board)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cached_entry_matches_computed() {
+        for fen in ["r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4", "8/8/8/4k3/8/8/8/3BNK2 w - - 0 1", "8/8/8/4k3/8/8/8/2BNK3 b - - 0 1"] {
+            let board = Board::from_fen(fen);
+            let (cached, computed) = (probe_material(&board), material_entry(&board));
+            assert_eq!((cached.phase, cached.imbalance), (computed.phase, computed.imbalance));
+            assert_eq!(cached.endgame.map(|(endgame, side)| (endgame.name, side)), computed.endgame.map(|(endgame, side)| (endgame.name, side)));
+        }
+        let board = Board::from_fen("8/8/8/4k3/8/8/8/3BNK2 w - - 0 1");
+        assert_eq!(probe_material(&board).endgame.unwrap().0.name, "KBNK");
+        assert_eq!(probe_material(&Board::start_pos()).imbalance, [board.eval_params.bishop_pair; 2]);
+    }
+}
@@ -1,3 +1,12 @@
+pub mod classical;
+pub mod endgame;
+pub mod eval_params;
 pub mod evaluation;
+pub mod hash_table;
+pub mod kpk;
+pub mod material;
+pub mod nnue;
 pub mod piece_square_tables;
+pub mod score;
+pub mod trace;
 pub use evaluation::*;
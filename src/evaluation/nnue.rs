@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use crate::board::piece::Piece;
+use crate::board::{Board, Side};
+
+const INPUT_SIZE: usize = 768; // 2 sides * 6 piece types * 64 squares
+const LANES: usize = 16;
+const QA: i32 = 255; // Quantization of the feature transformer
+const QB: i32 = 64; // Quantization of the output layer
+const SCALE: i32 = 400; // Network output in centipawns per unit of win probability logit
+
+/// Quantized 768->N->1 perspective network with a squared clipped ReLU activation, stored in
+/// the layout used by the bullet trainer: feature weights `[768][N]`, feature biases `[N]`,
+/// output weights `[2N]` and the output bias, all little endian `i16`.
+#[derive(Debug)]
+pub struct Network {
+    hidden_size: usize,
+    feature_weights: Vec<i16>,
+    feature_biases: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i16,
+}
+
+impl Network {
+    // Reads a network of any hidden layer size, which is inferred from the length of the data
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.len().is_multiple_of(2) {
+            return Err(format!("network size {} is not a whole number of weights", bytes.len()));
+        }
+        let values: Vec<i16> = bytes.chunks_exact(2).map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]])).collect();
+        // Trainers commonly pad the file to a multiple of 64 bytes, so ignore any trailing weights
+        let hidden_size = values.len().saturating_sub(1) / (INPUT_SIZE + 3);
+        if hidden_size == 0 {
+            return Err(format!("network size {} is too small", bytes.len()));
+        }
+
+        let (feature_weights, rest) = values.split_at(INPUT_SIZE * hidden_size);
+        let (feature_biases, rest) = rest.split_at(hidden_size);
+        let (output_weights, rest) = rest.split_at(2 * hidden_size);
+        Ok(Self {
+            hidden_size,
+            feature_weights: feature_weights.to_vec(),
+            feature_biases: feature_biases.to_vec(),
+            output_weights: output_weights.to_vec(),
+            output_bias: rest[0],
+        })
+    }
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = std::fs::read(path).map_err(|error| format!("could not read network {}: {}", path, error))?;
+        Self::from_bytes(&bytes)
+    }
+    pub fn hidden_size(&self) -> usize {
+        self.hidden_size
+    }
+
+    fn feature_weights(&self, feature: usize) -> &[i16] {
+        &self.feature_weights[feature * self.hidden_size..(feature + 1) * self.hidden_size]
+    }
+
+    // Forward pass of the output layer from the accumulators of the side to move and its opponent
+    fn output(&self, us: &[i16], them: &[i16]) -> i32 {
+        let (our_weights, their_weights) = self.output_weights.split_at(self.hidden_size);
+        let sum = screlu_dot(us, our_weights) + screlu_dot(them, their_weights);
+        (sum / QA + self.output_bias as i32) * SCALE / (QA * QB)
+    }
+}
+
+// Sum of clamp(x, 0, QA)^2 * w, with the clamped value applied to the weight first so the product
+// fits in i32 as long as the trainer clipped the output weights to below 2 * QB.
+fn screlu_dot(values: &[i16], weights: &[i16]) -> i32 {
+    // One partial sum per lane so the loop vectorizes, summed with wrapping like the lanes would
+    let mut sums = [0i32; LANES];
+    let mut value_chunks = values.chunks_exact(LANES);
+    let mut weight_chunks = weights.chunks_exact(LANES);
+    for (value, weight) in (&mut value_chunks).zip(&mut weight_chunks) {
+        for ((sum, &value), &weight) in sums.iter_mut().zip(value).zip(weight) {
+            let value = (value as i32).clamp(0, QA);
+            *sum = sum.wrapping_add(value * weight as i32 * value);
+        }
+    }
+    let mut sum = sums.iter().fold(0i32, |sum, &lane| sum.wrapping_add(lane));
+    for (&value, &weight) in value_chunks.remainder().iter().zip(weight_chunks.remainder()) {
+        let value = (value as i32).clamp(0, QA);
+        sum += value * weight as i32 * value;
+    }
+    sum
+}
+
+fn add_weights(values: &mut [i16], weights: &[i16]) {
+    for (value, &weight) in values.iter_mut().zip(weights) {
+        *value = value.wrapping_add(weight);
+    }
+}
+fn sub_weights(values: &mut [i16], weights: &[i16]) {
+    for (value, &weight) in values.iter_mut().zip(weights) {
+        *value = value.wrapping_sub(weight);
+    }
+}
+
+// Input index of a piece seen from one side, with that side's pieces first and its back rank as
+// rank 1. Squares are numbered from a8, so white flips the rank and black keeps it.
+fn feature_index(perspective: Side, piece: Piece, square: usize) -> usize {
+    let (relative_side, relative_square) = match perspective {
+        Side::White => (piece.side() as usize, square ^ 56),
+        Side::Black => (piece.side().enemy() as usize, square),
+    };
+    relative_side * 384 + piece.piece_type() as usize * 64 + relative_square
+}
+
+/// Hidden layer values for both perspectives, kept up to date by `Board` as pieces are added
+/// and removed so that evaluating only needs the output layer.
+#[derive(Clone, Debug)]
+pub struct Accumulator {
+    network: Arc<Network>,
+    values: [Vec<i16>; 2],
+}
+
+impl Accumulator {
+    pub fn new(network: Arc<Network>, board: &Board) -> Self {
+        let mut accumulator = Self {
+            values: [network.feature_biases.clone(), network.feature_biases.clone()],
+            network,
+        };
+        for square in 0..64 {
+            if let Some(piece) = board.squares[square] {
+                accumulator.add(piece, square);
+            }
+        }
+        accumulator
+    }
+    pub fn network(&self) -> &Arc<Network> {
+        &self.network
+    }
+
+    #[inline(always)]
+    pub fn add(&mut self, piece: Piece, square: usize) {
+        for perspective in [Side::White, Side::Black] {
+            add_weights(&mut self.values[perspective], self.network.feature_weights(feature_index(perspective, piece, square)));
+        }
+    }
+    #[inline(always)]
+    pub fn remove(&mut self, piece: Piece, square: usize) {
+        for perspective in [Side::White, Side::Black] {
+            sub_weights(&mut self.values[perspective], self.network.feature_weights(feature_index(perspective, piece, square)));
+        }
+    }
+
+    // Evaluation in centipawns from the perspective of the given side
+    pub fn evaluate(&self, side: Side) -> i32 {
+        self.network.output(&self.values[side], &self.values[side.enemy()])
+    }
+}
+
+impl PartialEq for Accumulator {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.network, &other.network) && self.values == other.values
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::move_generation::generate_moves;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    // Random network with a hidden size that also exercises the scalar remainder loops
+    fn random_network(hidden_size: usize) -> Network {
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        let weight_count = INPUT_SIZE * hidden_size + hidden_size + 2 * hidden_size + 1;
+        let bytes: Vec<u8> = (0..weight_count).flat_map(|_| rng.gen_range(-100i16..100).to_le_bytes()).collect();
+        Network::from_bytes(&bytes).unwrap()
+    }
+
+    #[test]
+    fn reads_hidden_size_from_length() {
+        assert_eq!(random_network(40).hidden_size(), 40);
+        assert!(Network::from_bytes(&[0; 10]).is_err());
+    }
+
+    #[test]
+    fn incremental_updates_match_refresh() {
+        let network = Arc::new(random_network(40));
+        let mut board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        board.set_network(Some(network.clone()));
+        let start = board.accumulator.clone();
+
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let mut moves = Vec::new();
+        for _ in 0..40 {
+            let legal_moves = generate_moves(&board);
+            if legal_moves.is_empty() {
+                break;
+            }
+            let mov = legal_moves[rng.gen_range(0..legal_moves.len())];
+            board.make_move(mov);
+            moves.push(mov);
+            assert!(board.accumulator == Some(Accumulator::new(network.clone(), &board)), "accumulator diverged after {}", mov);
+        }
+        for mov in moves.into_iter().rev() {
+            board.unmake_move(mov);
+        }
+        assert!(board.accumulator == start);
+    }
+
+    #[test]
+    fn evaluation_is_symmetric() {
+        let network = Arc::new(random_network(32));
+        let white = Accumulator::new(network.clone(), &Board::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+        let black = Accumulator::new(network, &Board::from_fen("4k3/4p3/8/8/8/8/8/4K3 b - - 0 1"));
+        assert_eq!(white.evaluate(Side::White), black.evaluate(Side::Black));
+    }
+}
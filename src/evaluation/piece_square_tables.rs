@@ -1,8 +1,5 @@
 #[rustfmt::skip]
 mod unformatted {
-    use crate::board::Side;
-    use crate::board::piece::PieceType;
-
     const MIDGAME_PAWN_SQUARE_TABLE: [i32; 64] = [
       0,   0,   0,   0,   0,   0,  0,   0,
      98, 134,  61,  95,  68, 126, 34, -11,
@@ -136,33 +133,8 @@ mod unformatted {
          6, 5, 4, 3, 3, 4, 5, 6
     ];
 
-    const MIDGAME_PIECE_SQUARE_TABLES: [[i32; 64]; 6] = [MIDGAME_PAWN_SQUARE_TABLE, MIDGAME_KNIGHT_SQUARE_TABLE, MIDGAME_BISHOP_SQUARE_TABLE, MIDGAME_ROOK_SQUARE_TABLE, MIDGAME_QUEEN_SQUARE_TABLE, MIDGAME_KING_SQUARE_TABLE];
-    const ENDGAME_PIECE_SQUARE_TABLES: [[i32; 64]; 6] = [ENDGAME_PAWN_SQUARE_TABLE, ENDGAME_KNIGHT_SQUARE_TABLE, ENDGAME_BISHOP_SQUARE_TABLE, ENDGAME_ROOK_SQUARE_TABLE, ENDGAME_QUEEN_SQUARE_TABLE, ENDGAME_KING_SQUARE_TABLE];
-
-    pub fn midgame_position_value(piece_type: PieceType, square: usize, side: Side) -> i32 {
-        match side {
-            Side::White => MIDGAME_PIECE_SQUARE_TABLES[piece_type][square],
-            Side::Black => {
-                let rank = square / 8;
-                let file = square % 8;
-                let actual_rank = 7 - rank;
-                let new_square = file + actual_rank * 8;
-                MIDGAME_PIECE_SQUARE_TABLES[piece_type][new_square as usize]
-          }
-        }
-    }
-    pub fn endgame_position_value(piece_type: PieceType, square: usize, side: Side) -> i32 {
-        match side {
-            Side::White => ENDGAME_PIECE_SQUARE_TABLES[piece_type][square],
-            Side::Black => {
-                let rank = square / 8;
-                let file = square % 8;
-                let actual_rank = 7 - rank;
-                let new_square = file + actual_rank * 8;
-                ENDGAME_PIECE_SQUARE_TABLES[piece_type][new_square as usize]
-            }
-        }
-    }
+    pub const MIDGAME_PIECE_SQUARE_TABLES: [[i32; 64]; 6] = [MIDGAME_PAWN_SQUARE_TABLE, MIDGAME_KNIGHT_SQUARE_TABLE, MIDGAME_BISHOP_SQUARE_TABLE, MIDGAME_ROOK_SQUARE_TABLE, MIDGAME_QUEEN_SQUARE_TABLE, MIDGAME_KING_SQUARE_TABLE];
+    pub const ENDGAME_PIECE_SQUARE_TABLES: [[i32; 64]; 6] = [ENDGAME_PAWN_SQUARE_TABLE, ENDGAME_KNIGHT_SQUARE_TABLE, ENDGAME_BISHOP_SQUARE_TABLE, ENDGAME_ROOK_SQUARE_TABLE, ENDGAME_QUEEN_SQUARE_TABLE, ENDGAME_KING_SQUARE_TABLE];
 }
 
 pub use unformatted::*;
@@ -0,0 +1,66 @@
+use derive_more::{Add, AddAssign, Neg, Sub, SubAssign};
+use std::fmt::{self, Debug};
+use std::ops::Mul;
+
+// Phase of the starting position, counting only non-pawn material
+pub const MAX_PHASE: i32 = 24;
+
+/// Midgame and endgame values packed into one integer, the endgame value in the upper 16 bits,
+/// so both are added and subtracted in a single operation. Each value has to fit in an i16.
+#[derive(Add, AddAssign, Sub, SubAssign, Neg, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Score(i32);
+
+pub const fn s(mg: i32, eg: i32) -> Score {
+    Score(((eg as u32) << 16) as i32 + mg)
+}
+
+impl Score {
+    pub const fn mg(self) -> i32 {
+        self.0 as u16 as i16 as i32
+    }
+    // Rounds so that a negative midgame value borrowing from the upper half is undone
+    pub const fn eg(self) -> i32 {
+        ((self.0.wrapping_add(0x8000) as u32 >> 16) as u16) as i16 as i32
+    }
+    // Interpolates between the endgame value at phase 0 and the midgame value at MAX_PHASE
+    pub fn taper(self, phase: i32) -> i32 {
+        (self.mg() * phase + self.eg() * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl Mul<i32> for Score {
+    type Output = Score;
+    fn mul(self, rhs: i32) -> Score {
+        Score(self.0 * rhs)
+    }
+}
+
+impl Debug for Score {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s({}, {})", self.mg(), self.eg())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packs_both_values() {
+        for (mg, eg) in [(0, 0), (15, -20), (-300, 450), (-1, -1), (20000, -20000)] {
+            let score = s(mg, eg);
+            assert_eq!((score.mg(), score.eg()), (mg, eg));
+        }
+        assert_eq!(s(10, -5) + s(-30, 8), s(-20, 3));
+        assert_eq!(s(10, -5) - s(-30, 8), s(40, -13));
+        assert_eq!(s(-7, 12) * 3, s(-21, 36));
+        assert_eq!(-s(-7, 12), s(7, -12));
+    }
+
+    #[test]
+    fn tapers_by_phase() {
+        assert_eq!(s(100, 200).taper(MAX_PHASE), 100);
+        assert_eq!(s(100, 200).taper(0), 200);
+        assert_eq!(s(100, 200).taper(MAX_PHASE / 2), 150);
+    }
+}
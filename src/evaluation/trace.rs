@@ -0,0 +1,173 @@
+use std::fmt::{self, Display};
+
+use crate::board::piece::{Piece, PieceType};
+use crate::board::{Board, Side};
+use crate::evaluation::classical::{side_terms, SideTerms};
+use crate::evaluation::endgame::{find_endgame, scale_factor, SCALE_NORMAL};
+use crate::evaluation::evaluation::{corner_king_evaluation, evaluate, phase};
+use crate::evaluation::score::{s, Score, MAX_PHASE};
+
+/// Breakdown of the hand crafted evaluation into its terms for each side, plus how much each
+/// piece contributes to the final score.
+#[derive(Clone, Debug)]
+pub struct EvalTrace {
+    pub squares: [Option<Piece>; 64],
+    pub side: Side,
+    pub phase: i32,
+    // Material and piece-square tables of each side, material has no endgame value of its own
+    pub material: [i32; 2],
+    pub piece_square: [Score; 2],
+    pub terms: [SideTerms; 2],
+    // Weighted and tapered, only applies to the side to move and is from that side's perspective
+    pub king_corner: i32,
+    pub tempo: Score,
+    // Specialized evaluator that replaces all of the terms, if there is one for the material
+    pub endgame: Option<&'static str>,
+    // Out of SCALE_NORMAL, for the side that is ahead
+    pub scale_factor: i32,
+    // Final tapered score of the hand crafted evaluation from white's perspective
+    pub total: i32,
+    // Score of the network for the side to move, if one is in use
+    pub nnue: Option<i32>,
+    // Change in the evaluation from white's perspective when the piece on the square is removed
+    pub piece_contributions: [Option<i32>; 64],
+}
+
+pub fn evaluate_trace(board: &Board) -> EvalTrace {
+    let mut hce_board = board.clone();
+    hce_board.set_network(None);
+    let params = &board.eval_params;
+
+    let mut material = [0; 2];
+    let mut piece_square = [Score::default(); 2];
+    for square in 0..64 {
+        if let Some(piece) = board.squares[square] {
+            if piece.piece_type() != PieceType::King {
+                material[piece.side()] += params.piece_value(piece.piece_type());
+            }
+            piece_square[piece.side()] += s(params.midgame_value(piece, square), params.endgame_value(piece, square));
+        }
+    }
+
+    let mut piece_contributions = [None; 64];
+    let total = evaluate(&hce_board) * board.side.factor();
+    for (square, contribution) in piece_contributions.iter_mut().enumerate() {
+        let Some(piece) = board.squares[square] else { continue };
+        if piece.piece_type() == PieceType::King {
+            continue;
+        }
+        let mut without_piece = hce_board.clone();
+        without_piece.squares[square] = None;
+        let mut without_piece = Board::from_fen(&without_piece.fen());
+        without_piece.set_eval_params(board.eval_params.clone());
+        *contribution = Some(total - evaluate(&without_piece) * without_piece.side.factor());
+    }
+
+    let phase = phase(board);
+    EvalTrace {
+        squares: board.squares,
+        side: board.side,
+        phase,
+        material,
+        piece_square,
+        terms: [side_terms(board, Side::White), side_terms(board, Side::Black)],
+        king_corner: corner_king_evaluation(board) * (MAX_PHASE - phase).pow(2) * params.king_corner_weight / (MAX_PHASE * MAX_PHASE),
+        tempo: params.tempo,
+        endgame: find_endgame(board).map(|(endgame, _)| endgame.name),
+        scale_factor: scale_factor(board, total * board.side.factor()),
+        total,
+        nnue: board.accumulator.as_ref().map(|accumulator| accumulator.evaluate(board.side)),
+        piece_contributions,
+    }
+}
+
+fn pawns(centipawns: f32) -> String {
+    format!("{:+.2}", centipawns / 100.0)
+}
+
+impl EvalTrace {
+    fn write_row(&self, f: &mut fmt::Formatter<'_>, name: &str, white: Score, black: Score) -> fmt::Result {
+        let total = white - black;
+        writeln!(
+            f,
+            "| {:>15} | {:>6} {:>6} | {:>6} {:>6} | {:>6} {:>6} | {:>7} |",
+            name,
+            pawns(white.mg() as f32),
+            pawns(white.eg() as f32),
+            pawns(black.mg() as f32),
+            pawns(black.eg() as f32),
+            pawns(total.mg() as f32),
+            pawns(total.eg() as f32),
+            pawns(total.taper(self.phase) as f32),
+        )
+    }
+
+    fn write_grid(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let separator = "+-------".repeat(8) + "+";
+        writeln!(f, "{}", separator)?;
+        for rank in 0..8 {
+            let mut pieces = String::new();
+            let mut values = String::new();
+            for file in 0..8 {
+                let square = rank * 8 + file;
+                let piece = self.squares[square].map_or(' ', |piece| piece.to_char());
+                pieces.push_str(&format!("|   {}   ", piece));
+                let value = self.piece_contributions[square].map_or(String::new(), |value| pawns(value as f32));
+                values.push_str(&format!("| {:^5} ", value));
+            }
+            writeln!(f, "{}|", pieces)?;
+            writeln!(f, "{}|", values)?;
+            writeln!(f, "{}", separator)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for EvalTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Contributing pieces (white's perspective, in pawns):")?;
+        self.write_grid(f)?;
+        writeln!(f)?;
+
+        let separator = "+-----------------+---------------+---------------+---------------+---------+";
+        writeln!(f, "{}", separator)?;
+        writeln!(f, "| {:>15} | {:>13} | {:>13} | {:>13} | {:>7} |", "Term", "White mg eg", "Black mg eg", "Total mg eg", "Tapered")?;
+        writeln!(f, "{}", separator)?;
+        let [white, black] = [Side::White, Side::Black].map(|side| s(self.material[side], self.material[side]));
+        self.write_row(f, "Material", white, black)?;
+        self.write_row(f, "Piece-square", self.piece_square[Side::White], self.piece_square[Side::Black])?;
+        for ((name, white), (_, black)) in self.terms[Side::White].named().into_iter().zip(self.terms[Side::Black].named()) {
+            self.write_row(f, name, white, black)?;
+        }
+        writeln!(f, "{}", separator)?;
+
+        writeln!(f, "King corner: {} for {:?}", pawns(self.king_corner as f32), self.side)?;
+        writeln!(f, "Tempo: {} for {:?}", pawns(self.tempo.taper(self.phase) as f32), self.side)?;
+        writeln!(f, "Phase: {}/{}", self.phase, MAX_PHASE)?;
+        if let Some(endgame) = self.endgame {
+            writeln!(f, "Endgame: {} replaces the terms above", endgame)?;
+        }
+        writeln!(f, "Scale factor: {}/{}", self.scale_factor, SCALE_NORMAL)?;
+        writeln!(f, "Classical evaluation: {} (white side)", pawns(self.total as f32))?;
+        if let Some(nnue) = self.nnue {
+            writeln!(f, "NNUE evaluation: {} (white side)", pawns((nnue * self.side.factor()) as f32))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trace_matches_evaluation() {
+        let board = Board::from_fen("r1bq1rk1/pp2nppp/2n1p3/3pP3/1b1P4/2NB1N2/PP3PPP/R1BQK2R b KQ - 0 8");
+        let trace = evaluate_trace(&board);
+        assert_eq!(trace.total, -evaluate(&board));
+        assert_eq!(trace.material[Side::White], trace.material[Side::Black]);
+        // Removing the knight on c3 loses at least the knight itself
+        assert!(trace.piece_contributions[42].unwrap() > 200);
+        assert!(trace.piece_contributions[4].is_none());
+    }
+}
@@ -1,8 +1,11 @@
-#![feature(portable_simd)]
-
 pub mod board;
 pub mod evaluation;
 pub mod move_generation;
 pub mod perft;
 pub mod bench;
+pub mod datagen;
+pub mod engine;
+#[cfg(feature = "render")]
+pub mod render;
 pub mod search;
+pub mod sprt;
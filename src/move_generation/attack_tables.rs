@@ -1,233 +1,241 @@
 use crate::board::bitboard::Bitboard;
-use crate::board::direction::{self, Direction};
+use crate::board::direction::Direction;
 use crate::board::piece_move::Square;
 use crate::board::Side;
+#[cfg(not(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2")))]
 use crate::move_generation::magic_numbers::*;
-use std::cmp::min;
-use std::num::Wrapping;
-use std::sync::LazyLock;
-use ctor::ctor;
 
-static mut SQUARES_TO_EDGE: [[u32; 8]; 64] = [[0; 8]; 64];
-static mut ATTACK_RAYS: [[Bitboard; 8]; 64] = [[Bitboard(0); 8]; 64];
-static mut KNIGHT_ATTACK_MASKS: [Bitboard; 64] = [Bitboard(0); 64];
-static mut KING_ATTACK_MASKS: [Bitboard; 64] = [Bitboard(0); 64];
-static mut BISHOP_ATTACK_MASKS: [Bitboard; 64] = [Bitboard(0); 64];
-static mut ROOK_ATTACK_MASKS: [Bitboard; 64] = [Bitboard(0); 64];
-static mut BISHOP_ATTACKS: [[Bitboard; 512]; 64] = [[Bitboard(0); 512]; 64];
-static mut ROOK_ATTACKS: [[Bitboard; 4096]; 64] = [[Bitboard(0); 4096]; 64];
-static mut PAWN_ATTACKS: [[Bitboard; 64]; 2] = [[Bitboard(0); 64]; 2];
-static mut BETWEEN_RAYS: [[Bitboard; 64]; 64] = [[Bitboard(0); 64]; 64];
-static mut LINE_RAYS: [[Bitboard; 64]; 64] = [[Bitboard(0); 64]; 64];
-static mut CHECKMASK_BETWEEN: [[Bitboard; 65]; 64] = [[Bitboard(0); 65]; 64];
-static mut ORTHOGONAL_RAYS: [Bitboard; 64] = [Bitboard(0); 64];
-static mut DIAGONAL_RAYS: [Bitboard; 64] = [Bitboard(0); 64];
-
-#[ctor]
-pub fn initialize_tables() {
-    unsafe {
-        SQUARES_TO_EDGE = precompute_squares_to_edge();
-        ATTACK_RAYS = precompute_attack_rays();
-        KNIGHT_ATTACK_MASKS = precompute_knight_attack_masks();
-        KING_ATTACK_MASKS = precompute_king_attack_masks();
-        BISHOP_ATTACK_MASKS = precompute_bishop_attack_masks();
-        ROOK_ATTACK_MASKS = precompute_rook_attack_masks();
-        BISHOP_ATTACKS = precompute_bishop_magic_bitboards();
-        ROOK_ATTACKS = precompute_rook_magic_bitboards();
-        PAWN_ATTACKS = precompute_pawn_attacks();
-        BETWEEN_RAYS = precompute_between_rays();
-        LINE_RAYS = precompute_line_rays();
-        CHECKMASK_BETWEEN = precompute_checkmask_between();
-        DIAGONAL_RAYS = precompute_diagonal_rays();
-        ORTHOGONAL_RAYS = precompute_orthogonal_rays();
-    }
-}
+/// Maps the blockers of a slider on one square to its attacks in a table shared by all squares:
+/// the blockers within the attack mask times the magic, shifted right, index the square's entries
+/// starting at the offset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Magic {
+    pub magic: u64,
+    pub shift: u32,
+    pub offset: usize,
+}
+
+impl Magic {
+    #[inline(always)]
+    pub const fn index(&self, blockers: u64) -> usize {
+        self.offset + (blockers.wrapping_mul(self.magic) >> self.shift) as usize
+    }
+}
+
+// Every table is computed by the compiler, so lookups need no initialization at startup
+static SQUARES_TO_EDGE: [[u32; 8]; 64] = precompute_squares_to_edge();
+static ATTACK_RAYS: [[Bitboard; 8]; 64] = precompute_attack_rays();
+static KNIGHT_ATTACK_MASKS: [Bitboard; 64] = precompute_knight_attack_masks();
+static KING_ATTACK_MASKS: [Bitboard; 64] = precompute_king_attack_masks();
+static BISHOP_ATTACK_MASKS: [Bitboard; 64] = precompute_slider_attack_masks(Direction::diagonal());
+static ROOK_ATTACK_MASKS: [Bitboard; 64] = precompute_slider_attack_masks(Direction::orthogonal());
+#[cfg(not(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2")))]
+static BISHOP_ATTACKS: [Bitboard; BISHOP_TABLE_SIZE] = precompute_magic_bitboards(&BISHOP_MAGICS, &BISHOP_ATTACK_MASKS, false);
+#[cfg(not(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2")))]
+static ROOK_ATTACKS: [Bitboard; ROOK_TABLE_SIZE] = precompute_magic_bitboards(&ROOK_MAGICS, &ROOK_ATTACK_MASKS, true);
+static PAWN_ATTACKS: [[Bitboard; 64]; 2] = precompute_pawn_attacks();
+static BETWEEN_RAYS: [[Bitboard; 64]; 64] = precompute_between_rays();
+static LINE_RAYS: [[Bitboard; 64]; 64] = precompute_line_rays();
+static CHECKMASK_BETWEEN: [[Bitboard; 65]; 64] = precompute_checkmask_between();
+static ORTHOGONAL_RAYS: [Bitboard; 64] = precompute_rays(Direction::orthogonal());
+static DIAGONAL_RAYS: [Bitboard; 64] = precompute_rays(Direction::diagonal());
+
+// With the pext feature and a target with BMI2, like `-C target-cpu=native` on a CPU that has it, the
+// slider tables are indexed by the blockers' bits packed together with pext instead of the magics,
+// so every square takes one entry per subset of its mask
+#[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+static BISHOP_PEXT_OFFSETS: [usize; 65] = precompute_pext_offsets(&BISHOP_ATTACK_MASKS);
+#[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+static ROOK_PEXT_OFFSETS: [usize; 65] = precompute_pext_offsets(&ROOK_ATTACK_MASKS);
+#[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+static BISHOP_PEXT_ATTACKS: [Bitboard; BISHOP_PEXT_OFFSETS[64]] = precompute_pext_bitboards(&BISHOP_PEXT_OFFSETS, &BISHOP_ATTACK_MASKS, false);
+#[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+static ROOK_PEXT_ATTACKS: [Bitboard; ROOK_PEXT_OFFSETS[64]] = precompute_pext_bitboards(&ROOK_PEXT_OFFSETS, &ROOK_ATTACK_MASKS, true);
 
 #[inline(always)]
 pub fn get_squares_to_edge(square: usize, direction: Direction) -> u32 {
-    unsafe {  
-        SQUARES_TO_EDGE[square][direction as usize]
-    }
+    SQUARES_TO_EDGE[square][direction as usize]
 }
 
 #[inline(always)]
 pub fn get_attack_ray(square: usize, direction: Direction) -> Bitboard {
-    unsafe {
-        ATTACK_RAYS[square][direction as usize]
-    }
+    ATTACK_RAYS[square][direction as usize]
 }
 
 #[inline(always)]
 pub fn get_knight_attack_mask(square: usize) -> Bitboard {
-    unsafe {
-        KNIGHT_ATTACK_MASKS[square]
-    }
+    KNIGHT_ATTACK_MASKS[square]
 }
 
 #[inline(always)]
 pub fn get_king_attack_mask(square: usize) -> Bitboard {
-    unsafe {
-        KING_ATTACK_MASKS[square]
-    }
+    KING_ATTACK_MASKS[square]
 }
 
 #[inline(always)]
 pub fn get_bishop_attack_mask(square: usize) -> Bitboard {
-    unsafe {
-        BISHOP_ATTACK_MASKS[square]
-    }
+    BISHOP_ATTACK_MASKS[square]
 }
 
 #[inline(always)]
 pub fn get_rook_attack_mask(square: usize) -> Bitboard {
-    unsafe {
-        ROOK_ATTACK_MASKS[square]
-    }
-}
-
-#[inline(always)]
-pub fn get_bishop_attack(square: usize, index: usize) -> Bitboard {
-    unsafe {
-        BISHOP_ATTACKS[square][index]
-    }
-}
-
-#[inline(always)]
-pub fn get_rook_attack(square: usize, index: usize) -> Bitboard {
-    unsafe {
-        ROOK_ATTACKS[square][index]
-    }
+    ROOK_ATTACK_MASKS[square]
 }
 
 #[inline(always)]
 pub fn get_pawn_attack(side: Side, square: usize) -> Bitboard {
-    unsafe {
-        PAWN_ATTACKS[side as usize][square]
-    }
+    PAWN_ATTACKS[side as usize][square]
 }
 
 #[inline(always)]
 pub fn get_between_ray(square1: usize, square2: usize) -> Bitboard {
-    unsafe {
-        BETWEEN_RAYS[square1][square2]
-    }
+    BETWEEN_RAYS[square1][square2]
 }
 
 #[inline(always)]
 pub fn get_line_ray(square1: usize, square2: usize) -> Bitboard {
-    unsafe {
-        LINE_RAYS[square1][square2]
-    }
+    LINE_RAYS[square1][square2]
 }
 
 #[inline(always)]
 pub fn get_checkmask_between(square1: usize, square2: usize) -> Bitboard {
-    unsafe {
-        CHECKMASK_BETWEEN[square1][square2]
-    }
+    CHECKMASK_BETWEEN[square1][square2]
 }
 #[inline(always)]
 pub fn get_orthogonal_rays(square: Square) -> Bitboard {
-    unsafe {
-        ORTHOGONAL_RAYS[square]
-    }
+    ORTHOGONAL_RAYS[square]
 }
 #[inline(always)]
 pub fn get_diagonal_rays(square: Square) -> Bitboard {
-    unsafe {
-        DIAGONAL_RAYS[square]
-    }
+    DIAGONAL_RAYS[square]
 }
 
+// Packs the blockers' bits under the mask into the low bits
+#[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+#[inline(always)]
+fn pext(blockers: Bitboard, mask: Bitboard) -> usize {
+    // Safe as the target is compiled for CPUs with BMI2
+    unsafe { std::arch::x86_64::_pext_u64(blockers.0, mask.0) as usize }
+}
+
+#[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
 #[inline]
 pub fn bishop_attacks(square: usize, blockers: Bitboard) -> Bitboard {
-    let mut index = Wrapping(blockers.0);
-    index &= get_bishop_attack_mask(square).0;
-    index *= BISHOP_MAGIC_NUMBERS[square];
-    index >>= 64 - BISHOP_SHIFT_AMOUNT[square] as usize;
-    get_bishop_attack(square, index.0 as usize)
+    BISHOP_PEXT_ATTACKS[BISHOP_PEXT_OFFSETS[square] + pext(blockers, BISHOP_ATTACK_MASKS[square])]
 }
+#[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
 #[inline]
 pub fn rook_attacks(square: usize, blockers: Bitboard) -> Bitboard {
-    let mut index = Wrapping(blockers.0);
-    index &= get_rook_attack_mask(square).0;
-    index *= ROOK_MAGIC_NUMBERS[square];
-    index >>= 64 - ROOK_SHIFT_AMOUNT[square] as usize;
-    get_rook_attack(square, index.0 as usize)
+    ROOK_PEXT_ATTACKS[ROOK_PEXT_OFFSETS[square] + pext(blockers, ROOK_ATTACK_MASKS[square])]
+}
+#[cfg(not(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2")))]
+#[inline]
+pub fn bishop_attacks(square: usize, blockers: Bitboard) -> Bitboard {
+    BISHOP_ATTACKS[BISHOP_MAGICS[square].index(blockers.0 & BISHOP_ATTACK_MASKS[square].0)]
+}
+#[cfg(not(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2")))]
+#[inline]
+pub fn rook_attacks(square: usize, blockers: Bitboard) -> Bitboard {
+    ROOK_ATTACKS[ROOK_MAGICS[square].index(blockers.0 & ROOK_ATTACK_MASKS[square].0)]
 }
 #[inline]
 pub fn queen_attacks(square: usize, blockers: Bitboard) -> Bitboard {
     bishop_attacks(square, blockers) | rook_attacks(square, blockers)
 }
 
-fn precompute_squares_to_edge() -> [[u32; 8]; 64] {
-    let mut squares_to_edge = [[0; 8]; 64];
-    for file in 0..8 {
-        for rank in 0..8 {
-            let north = rank as u32;
-            let south = (7 - rank) as u32;
-            let west = file as u32;
-            let east = (7 - file) as u32;
+// The tables below are built in const fns, which can't use iterators or the operator traits, so
+// they loop with while and work on the raw bits
+
+const fn min(a: u32, b: u32) -> u32 {
+    if a < b {
+        a
+    } else {
+        b
+    }
+}
 
-            let square = rank * 8 + file;
+// Square a number of steps away in a direction, which must stay on the board
+const fn step(square: usize, direction: Direction, distance: u32) -> usize {
+    (square as i32 + direction.value() * distance as i32) as usize
+}
 
-            squares_to_edge[square][Direction::North] = north;
-            squares_to_edge[square][Direction::South] = south;
-            squares_to_edge[square][Direction::West] = west;
-            squares_to_edge[square][Direction::East] = east;
-            squares_to_edge[square][Direction::NorthWest] = min(north, west);
-            squares_to_edge[square][Direction::NorthEast] = min(north, east);
-            squares_to_edge[square][Direction::SouthWest] = min(south, west);
-            squares_to_edge[square][Direction::SouthEast] = min(south, east);
-        }
+const fn precompute_squares_to_edge() -> [[u32; 8]; 64] {
+    let mut squares_to_edge = [[0; 8]; 64];
+    let mut square = 0;
+    while square < 64 {
+        let (file, rank) = ((square % 8) as u32, (square / 8) as u32);
+        let north = rank;
+        let south = 7 - rank;
+        let west = file;
+        let east = 7 - file;
+
+        squares_to_edge[square][Direction::North as usize] = north;
+        squares_to_edge[square][Direction::South as usize] = south;
+        squares_to_edge[square][Direction::West as usize] = west;
+        squares_to_edge[square][Direction::East as usize] = east;
+        squares_to_edge[square][Direction::NorthWest as usize] = min(north, west);
+        squares_to_edge[square][Direction::NorthEast as usize] = min(north, east);
+        squares_to_edge[square][Direction::SouthWest as usize] = min(south, west);
+        squares_to_edge[square][Direction::SouthEast as usize] = min(south, east);
+        square += 1;
     }
     squares_to_edge
 }
-fn precompute_attack_rays() -> [[Bitboard; 8]; 64] {
+const fn precompute_attack_rays() -> [[Bitboard; 8]; 64] {
     let mut attack_rays = [[Bitboard(0); 8]; 64];
-    for square in 0..64 {
-        let mut square_attack_rays = [Bitboard(0); 8];
-        for direction in Direction::all() {
-            let mut attack_ray = Bitboard(0);
-            for squares_to_edge in 1..get_squares_to_edge(square, direction) + 1 {
-                let end_square = square as i32 + direction.value() * squares_to_edge as i32;
-                attack_ray.set_bit(end_square as usize);
+    let mut square = 0;
+    while square < 64 {
+        let mut direction = 0;
+        while direction < 8 {
+            let mut distance = 1;
+            while distance <= SQUARES_TO_EDGE[square][direction] {
+                attack_rays[square][direction].set_bit(step(square, Direction::all()[direction], distance));
+                distance += 1;
             }
-            square_attack_rays[direction] = attack_ray;
+            direction += 1;
         }
-        attack_rays[square] = square_attack_rays;
+        square += 1;
     }
     attack_rays
 }
-fn precompute_between_rays() -> [[Bitboard; 64]; 64] {
+const fn precompute_between_rays() -> [[Bitboard; 64]; 64] {
     let mut between_rays = [[Bitboard(0); 64]; 64];
-    for square in 0..64 {
-        let mut square_between_rays = [Bitboard(0); 64];
-        for direction in Direction::all() {
-            for squares_to_edge in 1..get_squares_to_edge(square, direction) + 1 {
-                let end_square = (square as i32 + direction.value() * squares_to_edge as i32) as usize;
-                square_between_rays[end_square] = get_attack_ray(square, direction) ^ get_attack_ray(end_square, direction);
+    let mut square = 0;
+    while square < 64 {
+        let mut direction = 0;
+        while direction < 8 {
+            let mut distance = 1;
+            while distance <= SQUARES_TO_EDGE[square][direction] {
+                let end_square = step(square, Direction::all()[direction], distance);
+                between_rays[square][end_square] = Bitboard(ATTACK_RAYS[square][direction].0 ^ ATTACK_RAYS[end_square][direction].0);
+                distance += 1;
             }
+            direction += 1;
         }
-        between_rays[square] = square_between_rays;
+        square += 1;
     }
     between_rays
 }
-fn precompute_line_rays() -> [[Bitboard; 64]; 64] {
+const fn precompute_line_rays() -> [[Bitboard; 64]; 64] {
     let mut line_rays = [[Bitboard(0); 64]; 64];
-    for square in 0..64 {
-        let mut square_line_rays = [Bitboard(0); 64];
-        for direction in Direction::all() {
-            for squares_to_edge in 1..=get_squares_to_edge(square, direction) {
-                let end_square = (square as i32 + direction.value() * squares_to_edge as i32) as usize;
-                square_line_rays[end_square] = get_attack_ray(square, direction) | get_attack_ray(square, direction.opposite()) | Bitboard::from_square(square);
+    let mut square = 0;
+    while square < 64 {
+        let mut direction = 0;
+        while direction < 8 {
+            let opposite = Direction::all()[direction].opposite() as usize;
+            let line = ATTACK_RAYS[square][direction].0 | ATTACK_RAYS[square][opposite].0 | Bitboard::from_square(square).0;
+            let mut distance = 1;
+            while distance <= SQUARES_TO_EDGE[square][direction] {
+                line_rays[square][step(square, Direction::all()[direction], distance)] = Bitboard(line);
+                distance += 1;
             }
+            direction += 1;
         }
-        line_rays[square] = square_line_rays;
+        square += 1;
     }
     line_rays
 }
-fn precompute_knight_attack_masks() -> [Bitboard; 64] {
+const fn precompute_knight_attack_masks() -> [Bitboard; 64] {
     let mut knight_attack_masks = [Bitboard(0); 64];
     let directions = [
         Direction::North.value() * 2 + Direction::West.value(),
@@ -239,231 +247,210 @@ fn precompute_knight_attack_masks() -> [Bitboard; 64] {
         Direction::East.value() * 2 + Direction::North.value(),
         Direction::East.value() * 2 + Direction::South.value(),
     ];
-    for square in 0..64 {
-        let mut knight_attack_bitboard = Bitboard(0);
-        for direction in directions {
-            let end_square = square + direction;
-            if (0..64).contains(&end_square) && i32::abs(square % 8 - end_square % 8) <= 2 {
-                knight_attack_bitboard.set_bit(end_square as usize);
+    let mut square = 0;
+    while square < 64 {
+        let mut index = 0;
+        while index < directions.len() {
+            let end_square = square + directions[index];
+            if end_square >= 0 && end_square < 64 && (square % 8 - end_square % 8).abs() <= 2 {
+                knight_attack_masks[square as usize].set_bit(end_square as usize);
             }
+            index += 1;
         }
-        knight_attack_masks[square as usize] = knight_attack_bitboard;
+        square += 1;
     }
     knight_attack_masks
 }
-fn precompute_king_attack_masks() -> [Bitboard; 64] {
+const fn precompute_king_attack_masks() -> [Bitboard; 64] {
     let mut king_attack_masks = [Bitboard(0); 64];
-    for square in 0..64 {
-        let mut king_attack_bitboard = Bitboard(0);
-        for direction in Direction::all() {
-            let end_square = square + direction.value();
-            if (0..64).contains(&end_square) && i32::abs(square % 8 - end_square % 8) <= 2 {
-                king_attack_bitboard.set_bit(end_square as usize);
+    let mut square = 0;
+    while square < 64 {
+        let mut direction = 0;
+        while direction < 8 {
+            let end_square = square + Direction::all()[direction].value();
+            if end_square >= 0 && end_square < 64 && (square % 8 - end_square % 8).abs() <= 2 {
+                king_attack_masks[square as usize].set_bit(end_square as usize);
             }
+            direction += 1;
         }
-        king_attack_masks[square as usize] = king_attack_bitboard;
+        square += 1;
     }
     king_attack_masks
 }
-fn precompute_rook_attack_masks() -> [Bitboard; 64] {
-    let mut attack_masks = [Bitboard(0); 64];
-    for square in 0..64 {
-        let mut attack_mask = Bitboard(0);
-        for direction in Direction::orthogonal() {
-            for squares_to_edge in 1..get_squares_to_edge(square, direction) {
-                let end_square = square as i32 + direction.value() * squares_to_edge as i32;
-                attack_mask.set_bit(end_square as usize);
-            }
-        }
-        attack_masks[square] = attack_mask;
-    }
-    attack_masks
-}
-fn precompute_bishop_attack_masks() -> [Bitboard; 64] {
+// Squares whose pieces can block a slider, which leaves out the edge at the end of each ray
+const fn precompute_slider_attack_masks(directions: [Direction; 4]) -> [Bitboard; 64] {
     let mut attack_masks = [Bitboard(0); 64];
-    for square in 0..64 {
-        let mut attack_mask = Bitboard(0);
-        for direction in Direction::diagonal() {
-            for squares_to_edge in 1..get_squares_to_edge(square, direction) {
-                let end_square = square as i32 + direction.value() * squares_to_edge as i32;
-                attack_mask.set_bit(end_square as usize);
+    let mut square = 0;
+    while square < 64 {
+        let mut index = 0;
+        while index < directions.len() {
+            let direction = directions[index];
+            let mut distance = 1;
+            while distance < SQUARES_TO_EDGE[square][direction as usize] {
+                attack_masks[square].set_bit(step(square, direction, distance));
+                distance += 1;
             }
+            index += 1;
         }
-        attack_masks[square] = attack_mask;
+        square += 1;
     }
     attack_masks
 }
-fn precompute_pawn_attacks() -> [[Bitboard; 64]; 2] {
+const fn precompute_pawn_attacks() -> [[Bitboard; 64]; 2] {
     let mut pawn_attacks = [[Bitboard(0); 64]; 2];
-    for square in 0..64 {
+    let mut square = 0;
+    while square < 64 {
         let bitboard = Bitboard(1 << square);
-        pawn_attacks[Side::White][square] = bitboard.south_west() | bitboard.south_east();
-        pawn_attacks[Side::Black][square] = bitboard.north_west() | bitboard.north_east();
+        pawn_attacks[Side::White as usize][square] = Bitboard(bitboard.south_west().0 | bitboard.south_east().0);
+        pawn_attacks[Side::Black as usize][square] = Bitboard(bitboard.north_west().0 | bitboard.north_east().0);
+        square += 1;
     }
     pawn_attacks
 }
-fn precompute_checkmask_between() -> [[Bitboard; 65]; 64] {
-    let mut between_rays = [[Bitboard(0); 65]; 64];
-    for square in 0..64 {
-        let mut square_between_rays = [Bitboard(0); 65];
-        for direction in Direction::all() {
-            for squares_to_edge in 1..get_squares_to_edge(square, direction) + 1 {
-                let end_square = (square as i32 + direction.value() * squares_to_edge as i32) as usize;
-                square_between_rays[end_square] = get_attack_ray(square, direction) ^ get_attack_ray(end_square, direction);
-            }
-        }
-        for square1 in 0..64 {
-            if get_between_ray(square, square1) == 0 {
-                square_between_rays[square1] = Bitboard::from_square(square1);
-            }
+// Between rays that are also usable as check masks: a non-slider checker can only be captured,
+// and index 64 stands for no checker at all
+const fn precompute_checkmask_between() -> [[Bitboard; 65]; 64] {
+    let mut checkmask_between = [[Bitboard(0); 65]; 64];
+    let mut square = 0;
+    while square < 64 {
+        let mut square1 = 0;
+        while square1 < 64 {
+            checkmask_between[square][square1] = match BETWEEN_RAYS[square][square1].0 {
+                0 => Bitboard::from_square(square1),
+                between => Bitboard(between),
+            };
+            square1 += 1;
         }
-        square_between_rays[64] = Bitboard(u64::MAX);
-        between_rays[square] = square_between_rays;
+        checkmask_between[square][64] = Bitboard(u64::MAX);
+        square += 1;
     }
-    between_rays
+    checkmask_between
 }
-pub fn get_bishop_attacks_classical(square: usize, blockers: Bitboard) -> Bitboard {
-    let mut attacks = Bitboard(0);
-
-    attacks |= get_attack_ray(square, Direction::NorthWest);
-    if get_attack_ray(square, Direction::NorthWest) & blockers != 0 {
-        let blocker_index = (get_attack_ray(square, Direction::NorthWest) & blockers).msb();
-        attacks &= !get_attack_ray(blocker_index, Direction::NorthWest);
-    }
-
-    attacks |= get_attack_ray(square, Direction::NorthEast);
-    if get_attack_ray(square, Direction::NorthEast) & blockers != 0 {
-        let blocker_index = (get_attack_ray(square, Direction::NorthEast) & blockers).msb();
-        attacks &= !get_attack_ray(blocker_index, Direction::NorthEast);
-    }
-
-    attacks |= get_attack_ray(square, Direction::SouthWest);
-    if get_attack_ray(square, Direction::SouthWest) & blockers != 0 {
-        let blocker_index = (get_attack_ray(square, Direction::SouthWest) & blockers).lsb();
-        attacks &= !get_attack_ray(blocker_index, Direction::SouthWest);
-    }
-
-    attacks |= get_attack_ray(square, Direction::SouthEast);
-    if get_attack_ray(square, Direction::SouthEast) & blockers != 0 {
-        let blocker_index = (get_attack_ray(square, Direction::SouthEast) & blockers).lsb();
-        attacks &= !get_attack_ray(blocker_index, Direction::SouthEast);
+const fn precompute_rays(directions: [Direction; 4]) -> [Bitboard; 64] {
+    let mut bitboards = [Bitboard(0); 64];
+    let mut square = 0;
+    while square < 64 {
+        let mut index = 0;
+        while index < directions.len() {
+            bitboards[square].0 |= ATTACK_RAYS[square][directions[index] as usize].0;
+            index += 1;
+        }
+        square += 1;
     }
-
-    attacks
+    bitboards
 }
-pub fn get_rook_attacks_classical(square: usize, blockers: Bitboard) -> Bitboard {
-    let mut attacks = Bitboard(0);
-
-    attacks |= get_attack_ray(square, Direction::North);
-    if get_attack_ray(square, Direction::North) & blockers != 0 {
-        let blocker_index = (get_attack_ray(square, Direction::North) & blockers).msb();
-        attacks &= !get_attack_ray(blocker_index, Direction::North);
-    }
-
-    attacks |= get_attack_ray(square, Direction::West);
-    if get_attack_ray(square, Direction::West) & blockers != 0 {
-        let blocker_index = (get_attack_ray(square, Direction::West) & blockers).msb();
-        attacks &= !get_attack_ray(blocker_index, Direction::West);
-    }
-
-    attacks |= get_attack_ray(square, Direction::South);
-    if get_attack_ray(square, Direction::South) & blockers != 0 {
-        let blocker_index = (get_attack_ray(square, Direction::South) & blockers).lsb();
-        attacks &= !get_attack_ray(blocker_index, Direction::South);
-    }
-
-    attacks |= get_attack_ray(square, Direction::East);
-    if get_attack_ray(square, Direction::East) & blockers != 0 {
-        let blocker_index = (get_attack_ray(square, Direction::East) & blockers).lsb();
-        attacks &= !get_attack_ray(blocker_index, Direction::East);
-    }
-
-    attacks
-}
-fn precompute_bishop_magic_bitboards() -> [[Bitboard; 512]; 64] {
-    let mut bishop_attacks = [[Bitboard(0); 512]; 64];
-    for square in 0..64 {
-        for blocker_combination in 0..(1 << get_bishop_attack_mask(square).count_ones() as u64) + 1 {
-            let mut attack_mask = get_bishop_attack_mask(square);
-            let mut blockers = Bitboard(0);
 
-            let mut blocker_count = 0;
-            while attack_mask.0 != 0 {
-                let blocker_index = attack_mask.pop_lsb();
-                if (blocker_combination >> blocker_count) & 1 == 1 {
-                    blockers.set_bit(blocker_index);
-                }
-                blocker_count += 1;
+// Attacks along a ray up to and including the first blocker. Rays going north or west run towards
+// lower squares, so their first blocker is the most significant bit.
+const fn ray_attacks(square: usize, direction: Direction, blockers: Bitboard) -> u64 {
+    let ray = ATTACK_RAYS[square][direction as usize].0;
+    if ray & blockers.0 == 0 {
+        return ray;
+    }
+    let blocker_index = match direction {
+        Direction::North | Direction::West | Direction::NorthWest | Direction::NorthEast => Bitboard(ray & blockers.0).msb(),
+        _ => Bitboard(ray & blockers.0).lsb(),
+    };
+    ray & !ATTACK_RAYS[blocker_index][direction as usize].0
+}
+pub const fn get_bishop_attacks_classical(square: usize, blockers: Bitboard) -> Bitboard {
+    Bitboard(ray_attacks(square, Direction::NorthWest, blockers) | ray_attacks(square, Direction::NorthEast, blockers) | ray_attacks(square, Direction::SouthWest, blockers) | ray_attacks(square, Direction::SouthEast, blockers))
+}
+pub const fn get_rook_attacks_classical(square: usize, blockers: Bitboard) -> Bitboard {
+    Bitboard(ray_attacks(square, Direction::North, blockers) | ray_attacks(square, Direction::West, blockers) | ray_attacks(square, Direction::South, blockers) | ray_attacks(square, Direction::East, blockers))
+}
+const fn classical_attacks(square: usize, blockers: Bitboard, rook: bool) -> Bitboard {
+    if rook {
+        get_rook_attacks_classical(square, blockers)
+    } else {
+        get_bishop_attacks_classical(square, blockers)
+    }
+}
+// Every subset of the blocker mask is visited with the carry rippler trick, which steps through
+// them as if the mask bits were the bits of a counter. The tables of the squares may overlap, but
+// only where their attacks agree, so magics that don't fit together fail to compile.
+#[cfg(not(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2")))]
+const fn precompute_magic_bitboards<const N: usize>(magics: &[Magic; 64], masks: &[Bitboard; 64], rook: bool) -> [Bitboard; N] {
+    let mut table = [Bitboard(0); N];
+    let mut square = 0;
+    while square < 64 {
+        let mask = masks[square].0;
+        let mut blockers: u64 = 0;
+        loop {
+            let attacks = classical_attacks(square, Bitboard(blockers), rook);
+            let entry = &mut table[magics[square].index(blockers)];
+            if entry.0 != 0 && entry.0 != attacks.0 {
+                panic!("magics map blockers with different attacks to the same entry");
             }
-
-            let square_bishop_attacks = get_bishop_attacks_classical(square, blockers);
-            let mut index = Wrapping(blockers.0);
-            index *= BISHOP_MAGIC_NUMBERS[square];
-            index >>= 64 - BISHOP_SHIFT_AMOUNT[square] as usize;
-            bishop_attacks[square][index.0 as usize] = square_bishop_attacks;
-        }
-    }
-    bishop_attacks
-}
-fn precompute_rook_magic_bitboards() -> [[Bitboard; 4096]; 64] {
-    let mut rook_attacks = [[Bitboard(0); 4096]; 64];
-    let max_blocker_count = get_rook_attack_mask(0).count_ones() as u64;
-    for square in 0..64 {
-        for blocker_combination in 0..(1 << max_blocker_count) + 1 {
-            let mut attack_mask = get_rook_attack_mask(square);
-            let mut blockers = Bitboard(0);
-
-            let mut blocker_count = 0;
-            while attack_mask.0 != 0 {
-                let blocker_index = attack_mask.pop_lsb();
-                if (blocker_combination >> blocker_count) & 1 == 1 {
-                    blockers.set_bit(blocker_index);
-                }
-                blocker_count += 1;
+            *entry = attacks;
+            blockers = blockers.wrapping_sub(mask) & mask;
+            if blockers == 0 {
+                break;
             }
-
-            let square_rook_attacks = get_rook_attacks_classical(square, blockers);
-            let mut index = Wrapping(blockers.0);
-            index *= ROOK_MAGIC_NUMBERS[square];
-            index >>= 64 - ROOK_SHIFT_AMOUNT[square] as usize;
-            rook_attacks[square][index.0 as usize] = square_rook_attacks;
-        }
-    }
-    rook_attacks
-}
-fn precompute_orthogonal_rays() -> [Bitboard; 64] {
-    let mut bitboards = [Bitboard(0); 64];
-    for square in 0..64 {
-        let mut bitboard = Bitboard(0);
-        for direction in Direction::orthogonal() {
-            bitboard |= get_attack_ray(square, direction);
         }
-        bitboards[square] = bitboard;
-    }
-    bitboards
-}
-fn precompute_diagonal_rays() -> [Bitboard; 64] {
-    let mut bitboards = [Bitboard(0); 64];
-    for square in 0..64 {
-        let mut bitboard = Bitboard(0);
-        for direction in Direction::diagonal() {
-            bitboard |= get_attack_ray(square, direction);
+        square += 1;
+    }
+    table
+}
+#[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+const fn precompute_pext_offsets(masks: &[Bitboard; 64]) -> [usize; 65] {
+    let mut offsets = [0; 65];
+    let mut square = 0;
+    while square < 64 {
+        offsets[square + 1] = offsets[square] + (1 << masks[square].0.count_ones());
+        square += 1;
+    }
+    offsets
+}
+// The carry rippler visits the subsets in the order of their pext index
+#[cfg(all(feature = "pext", target_arch = "x86_64", target_feature = "bmi2"))]
+const fn precompute_pext_bitboards<const N: usize>(offsets: &[usize; 65], masks: &[Bitboard; 64], rook: bool) -> [Bitboard; N] {
+    let mut table = [Bitboard(0); N];
+    let mut square = 0;
+    while square < 64 {
+        let mask = masks[square].0;
+        let mut blockers: u64 = 0;
+        let mut index = offsets[square];
+        loop {
+            table[index] = classical_attacks(square, Bitboard(blockers), rook);
+            index += 1;
+            blockers = blockers.wrapping_sub(mask) & mask;
+            if blockers == 0 {
+                break;
+            }
         }
-        bitboards[square] = bitboard;
+        square += 1;
     }
-    bitboards
+    table
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::move_generation::magic_finder::subsets;
 
     #[test]
     fn test_number_of_squares_to_edge() {
-        //assert_eq!(SQUARES_TO_EDGE[4][Direction::East], 3);
-        //assert_eq!(SQUARES_TO_EDGE[27][Direction::North], 3);
-        //assert_eq!(SQUARES_TO_EDGE[47][Direction::South], 2);
-        //assert_eq!(SQUARES_TO_EDGE[21][Direction::West], 5);
+        assert_eq!(get_squares_to_edge(4, Direction::East), 3);
+        assert_eq!(get_squares_to_edge(27, Direction::North), 3);
+        assert_eq!(get_squares_to_edge(47, Direction::South), 2);
+        assert_eq!(get_squares_to_edge(21, Direction::West), 5);
+    }
+    #[test]
+    fn slider_tables_match_classical_attacks() {
+        let outside = 0x9c3a_51e0_2b84_d617; // Pieces outside the masks must not change the attacks
+        for square in 0..64 {
+            for blockers in subsets(get_rook_attack_mask(square).0) {
+                let expected = get_rook_attacks_classical(square, Bitboard(blockers));
+                assert_eq!(rook_attacks(square, Bitboard(blockers)), expected);
+                assert_eq!(rook_attacks(square, Bitboard(blockers | (outside & !get_rook_attack_mask(square).0))), expected);
+            }
+            for blockers in subsets(get_bishop_attack_mask(square).0) {
+                let expected = get_bishop_attacks_classical(square, Bitboard(blockers));
+                assert_eq!(bishop_attacks(square, Bitboard(blockers)), expected);
+                assert_eq!(bishop_attacks(square, Bitboard(blockers | (outside & !get_bishop_attack_mask(square).0))), expected);
+            }
+        }
     }
     #[test]
     fn test_rook_magic_bitboards_indexing() {
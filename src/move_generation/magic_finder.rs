@@ -0,0 +1,150 @@
+use std::cmp::Reverse;
+use std::fmt::Write;
+
+use rand::Rng;
+
+use crate::board::bitboard::Bitboard;
+use crate::board::piece::PieceType;
+use crate::move_generation::attack_tables::{get_bishop_attack_mask, get_bishop_attacks_classical, get_rook_attack_mask, get_rook_attacks_classical, Magic};
+
+/// Magics for all squares of a slider together with the size of the table they index.
+pub struct MagicTable {
+    pub magics: [Magic; 64],
+    pub size: usize,
+}
+
+// Every subset of a mask, starting with the empty one, visited with the carry rippler trick
+pub fn subsets(mask: u64) -> impl Iterator<Item = u64> {
+    std::iter::successors(Some(0), move |&subset: &u64| {
+        let next = subset.wrapping_sub(mask) & mask;
+        (next != 0).then_some(next)
+    })
+}
+
+type MaskFn = fn(usize) -> Bitboard;
+type AttacksFn = fn(usize, Bitboard) -> Bitboard;
+
+// Blocker mask and attacks of a bishop or a rook on a square
+fn slider(piece_type: PieceType) -> (MaskFn, AttacksFn) {
+    match piece_type {
+        PieceType::Bishop => (get_bishop_attack_mask, get_bishop_attacks_classical),
+        PieceType::Rook => (get_rook_attack_mask, get_rook_attacks_classical),
+        _ => panic!("only bishops and rooks are looked up with magics"),
+    }
+}
+
+// Random magic that maps the blockers of every occupancy to an index holding its attacks, with as
+// many index bits as the mask has squares. Occupancies with the same attacks may share an index.
+pub fn find_magic(occupancies: &[(u64, u64)], mask: u64, rng: &mut impl Rng) -> u64 {
+    let shift = 64 - mask.count_ones();
+    let mut used = vec![0; 1 << mask.count_ones()];
+    let mut tried_in = vec![0; 1 << mask.count_ones()]; // Saves clearing `used` between attempts
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        // Magics with few bits set are much more likely to work
+        let magic = rng.gen::<u64>() & rng.gen::<u64>() & rng.gen::<u64>();
+        if (mask.wrapping_mul(magic) >> 56).count_ones() < 6 {
+            continue;
+        }
+        let fits = occupancies.iter().all(|&(blockers, attacks)| {
+            let index = (blockers.wrapping_mul(magic) >> shift) as usize;
+            if tried_in[index] != attempt {
+                tried_in[index] = attempt;
+                used[index] = attacks;
+            }
+            used[index] == attacks
+        });
+        if fits {
+            return magic;
+        }
+    }
+}
+
+// Number of indices past the last one the magic uses. Most magics use every index, but the ones
+// mapping occupancies with the same attacks together can leave the end of the square's table empty,
+// where the next square's table starts.
+fn unused_tail(occupancies: &[(u64, u64)], magic: u64, bits: u32) -> usize {
+    let last = occupancies.iter().map(|&(blockers, _)| (blockers.wrapping_mul(magic) >> (64 - bits)) as usize).max().unwrap();
+    (1 << bits) - 1 - last
+}
+
+// Lowest offset at which the entries fit in the table, sharing the slots that already hold the same
+// attacks. Attacks are never empty, so an empty slot is free.
+fn lowest_offset(table: &[u64], entries: &[(usize, u64)]) -> usize {
+    (0..).find(|&offset| entries.iter().all(|&(index, attacks)| table.get(offset + index).is_none_or(|&entry| entry == 0 || entry == attacks))).unwrap()
+}
+
+// Finds magics for every square of a bishop or a rook and packs their tables into one, each square
+// at the lowest offset where its entries fit, sharing the slots where they agree with the squares
+// already placed. Each square keeps the one of `candidates` magics that leaves the most of the end
+// of its table empty.
+pub fn find_magics(piece_type: PieceType, candidates: usize, rng: &mut impl Rng) -> MagicTable {
+    let (mask_of, attacks_of) = slider(piece_type);
+    // Squares with the largest tables go first, the smaller ones then fill the gaps between them
+    let mut squares: Vec<usize> = (0..64).collect();
+    squares.sort_by_key(|&square| Reverse(mask_of(square).count_ones()));
+
+    let mut table: Vec<u64> = Vec::new();
+    let mut magics = [Magic { magic: 0, shift: 0, offset: 0 }; 64];
+    for square in squares {
+        let mask = mask_of(square).0;
+        let occupancies: Vec<(u64, u64)> = subsets(mask).map(|blockers| (blockers, attacks_of(square, Bitboard(blockers)).0)).collect();
+        let bits = mask.count_ones();
+        let magic = (0..candidates).map(|_| find_magic(&occupancies, mask, rng)).max_by_key(|&magic| unused_tail(&occupancies, magic, bits)).unwrap();
+        let entries: Vec<(usize, u64)> = occupancies.iter().map(|&(blockers, attacks)| ((blockers.wrapping_mul(magic) >> (64 - bits)) as usize, attacks)).collect();
+        let offset = lowest_offset(&table, &entries);
+        for (index, attacks) in entries {
+            if table.len() <= offset + index {
+                table.resize(offset + index + 1, 0);
+            }
+            table[offset + index] = attacks;
+        }
+        magics[square] = Magic { magic, shift: 64 - bits, offset };
+    }
+    MagicTable { magics, size: table.len() }
+}
+
+// Contents of magic_numbers.rs for the given tables
+pub fn magic_numbers_source(rook: &MagicTable, bishop: &MagicTable) -> String {
+    let mut source = String::new();
+    writeln!(source, "// Generated by the magics binary, `cargo run --release --bin magics > src/move_generation/magic_numbers.rs`.").unwrap();
+    writeln!(source, "// The tables of the squares overlap where one has no entry or their attacks agree, so each square").unwrap();
+    writeln!(source, "// starts at an offset into one table shared by all squares.").unwrap();
+    writeln!(source, "use crate::move_generation::attack_tables::Magic;").unwrap();
+    for (name, table) in [("ROOK", rook), ("BISHOP", bishop)] {
+        writeln!(source).unwrap();
+        writeln!(source, "pub const {}_TABLE_SIZE: usize = {};", name, table.size).unwrap();
+        writeln!(source, "pub const {}_MAGICS: [Magic; 64] = [", name).unwrap();
+        for magic in table.magics {
+            writeln!(source, "    Magic {{ magic: 0x{:016x}, shift: {}, offset: {} }},", magic.magic, magic.shift, magic.offset).unwrap();
+        }
+        writeln!(source, "];").unwrap();
+    }
+    source
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn packs_bishop_magics() {
+        // A seed where one of the few candidates leaves the end of its table empty
+        let mut rng = rand_chacha::ChaCha8Rng::seed_from_u64(5);
+        let table = find_magics(PieceType::Bishop, 10, &mut rng);
+        let fancy_size: usize = (0..64).map(|square| 1 << get_bishop_attack_mask(square).count_ones()).sum();
+        assert!(table.size < fancy_size);
+
+        let mut entries = vec![0; table.size];
+        for (square, magic) in table.magics.iter().enumerate() {
+            for blockers in subsets(get_bishop_attack_mask(square).0) {
+                let attacks = get_bishop_attacks_classical(square, Bitboard(blockers)).0;
+                let entry = &mut entries[magic.offset + (blockers.wrapping_mul(magic.magic) >> magic.shift) as usize];
+                assert!(*entry == 0 || *entry == attacks, "square {} collides", square);
+                *entry = attacks;
+            }
+        }
+    }
+}
@@ -1,139 +1,140 @@
-pub const ROOK_MAGIC_NUMBERS: [u64; 64] = [
-    0x8a80104000800020,
-    0x140002000100040,
-    0x2801880a0017001,
-    0x100081001000420,
-    0x200020010080420,
-    0x3001c0002010008,
-    0x8480008002000100,
-    0x2080088004402900,
-    0x800098204000,
-    0x2024401000200040,
-    0x100802000801000,
-    0x120800800801000,
-    0x208808088000400,
-    0x2802200800400,
-    0x2200800100020080,
-    0x801000060821100,
-    0x80044006422000,
-    0x100808020004000,
-    0x12108a0010204200,
-    0x140848010000802,
-    0x481828014002800,
-    0x8094004002004100,
-    0x4010040010010802,
-    0x20008806104,
-    0x100400080208000,
-    0x2040002120081000,
-    0x21200680100081,
-    0x20100080080080,
-    0x2000a00200410,
-    0x20080800400,
-    0x80088400100102,
-    0x80004600042881,
-    0x4040008040800020,
-    0x440003000200801,
-    0x4200011004500,
-    0x188020010100100,
-    0x14800401802800,
-    0x2080040080800200,
-    0x124080204001001,
-    0x200046502000484,
-    0x480400080088020,
-    0x1000422010034000,
-    0x30200100110040,
-    0x100021010009,
-    0x2002080100110004,
-    0x202008004008002,
-    0x20020004010100,
-    0x2048440040820001,
-    0x101002200408200,
-    0x40802000401080,
-    0x4008142004410100,
-    0x2060820c0120200,
-    0x1001004080100,
-    0x20c020080040080,
-    0x2935610830022400,
-    0x44440041009200,
-    0x280001040802101,
-    0x2100190040002085,
-    0x80c0084100102001,
-    0x4024081001000421,
-    0x20030a0244872,
-    0x12001008414402,
-    0x2006104900a0804,
-    0x1004081002402,
+// Generated by the magics binary, `cargo run --release --bin magics > src/move_generation/magic_numbers.rs`.
+// The tables of the squares overlap where one has no entry or their attacks agree, so each square
+// starts at an offset into one table shared by all squares.
+use crate::move_generation::attack_tables::Magic;
+
+pub const ROOK_TABLE_SIZE: usize = 102400;
+pub const ROOK_MAGICS: [Magic; 64] = [
+    Magic { magic: 0x0100108000402100, shift: 52, offset: 0 },
+    Magic { magic: 0x128010400a802000, shift: 53, offset: 16384 },
+    Magic { magic: 0x170008a001001040, shift: 53, offset: 18432 },
+    Magic { magic: 0x0100080410010020, shift: 53, offset: 20480 },
+    Magic { magic: 0x0300020801005004, shift: 53, offset: 22528 },
+    Magic { magic: 0x0500030002240008, shift: 53, offset: 24576 },
+    Magic { magic: 0x3080800082000100, shift: 53, offset: 26624 },
+    Magic { magic: 0x0200040040821021, shift: 52, offset: 4096 },
+    Magic { magic: 0xa804800480400020, shift: 53, offset: 28672 },
+    Magic { magic: 0x8184400420005000, shift: 54, offset: 65536 },
+    Magic { magic: 0x1000802000100082, shift: 54, offset: 66560 },
+    Magic { magic: 0x0008800800801000, shift: 54, offset: 67584 },
+    Magic { magic: 0x00248048000c0080, shift: 54, offset: 68608 },
+    Magic { magic: 0x0102000804020010, shift: 54, offset: 69632 },
+    Magic { magic: 0x0881010002000401, shift: 54, offset: 70656 },
+    Magic { magic: 0x0001002b00068042, shift: 53, offset: 30720 },
+    Magic { magic: 0x022222800090c000, shift: 53, offset: 32768 },
+    Magic { magic: 0x0120088040008020, shift: 54, offset: 71680 },
+    Magic { magic: 0x9005010014402004, shift: 54, offset: 72704 },
+    Magic { magic: 0x0c00808010000800, shift: 54, offset: 73728 },
+    Magic { magic: 0x0801010004100800, shift: 54, offset: 74752 },
+    Magic { magic: 0x0100808002000400, shift: 54, offset: 75776 },
+    Magic { magic: 0x41c0040081100208, shift: 54, offset: 76800 },
+    Magic { magic: 0x0008520001004094, shift: 53, offset: 34816 },
+    Magic { magic: 0x0000400780046882, shift: 53, offset: 36864 },
+    Magic { magic: 0x8550004040002000, shift: 54, offset: 77824 },
+    Magic { magic: 0x0102008200401021, shift: 54, offset: 78848 },
+    Magic { magic: 0x110c100100200900, shift: 54, offset: 79872 },
+    Magic { magic: 0x8023000500080091, shift: 54, offset: 80896 },
+    Magic { magic: 0x2048040080800200, shift: 54, offset: 81920 },
+    Magic { magic: 0x0002000200048108, shift: 54, offset: 82944 },
+    Magic { magic: 0x8129004a00011084, shift: 53, offset: 38912 },
+    Magic { magic: 0xc04000844a800020, shift: 53, offset: 40960 },
+    Magic { magic: 0x14f0400880802001, shift: 54, offset: 83968 },
+    Magic { magic: 0x4000401101002001, shift: 54, offset: 84992 },
+    Magic { magic: 0x8001002409001000, shift: 54, offset: 86016 },
+    Magic { magic: 0x80a4050011000800, shift: 54, offset: 87040 },
+    Magic { magic: 0x0042000802000410, shift: 54, offset: 88064 },
+    Magic { magic: 0x0510020184001008, shift: 54, offset: 89088 },
+    Magic { magic: 0x0920008042001401, shift: 53, offset: 43008 },
+    Magic { magic: 0x0034400480008020, shift: 53, offset: 45056 },
+    Magic { magic: 0x0020200040008080, shift: 54, offset: 90112 },
+    Magic { magic: 0x0004801022020040, shift: 54, offset: 91136 },
+    Magic { magic: 0x0150000800108080, shift: 54, offset: 92160 },
+    Magic { magic: 0x0541000800050010, shift: 54, offset: 93184 },
+    Magic { magic: 0x5842001104020008, shift: 54, offset: 94208 },
+    Magic { magic: 0x0009000200e10004, shift: 54, offset: 95232 },
+    Magic { magic: 0x2011440080420001, shift: 53, offset: 47104 },
+    Magic { magic: 0x2480002000400440, shift: 53, offset: 49152 },
+    Magic { magic: 0x4088210040860200, shift: 54, offset: 96256 },
+    Magic { magic: 0x0020022010028480, shift: 54, offset: 97280 },
+    Magic { magic: 0x0182210208100100, shift: 54, offset: 98304 },
+    Magic { magic: 0x0106000860100600, shift: 54, offset: 99328 },
+    Magic { magic: 0x0802000204008080, shift: 54, offset: 100352 },
+    Magic { magic: 0x0000011278104400, shift: 54, offset: 101376 },
+    Magic { magic: 0x4208004100840200, shift: 53, offset: 51200 },
+    Magic { magic: 0x640820c211018001, shift: 52, offset: 8192 },
+    Magic { magic: 0x1022010040268016, shift: 53, offset: 53248 },
+    Magic { magic: 0x0684120280602842, shift: 53, offset: 55296 },
+    Magic { magic: 0x9009080421011001, shift: 53, offset: 57344 },
+    Magic { magic: 0x000200142860304a, shift: 53, offset: 59392 },
+    Magic { magic: 0x600200010830c402, shift: 53, offset: 61440 },
+    Magic { magic: 0x0220008110022804, shift: 53, offset: 63488 },
+    Magic { magic: 0x1001000040208201, shift: 52, offset: 12288 },
 ];
-pub const BISHOP_MAGIC_NUMBERS: [u64; 64] = [
-    0x40040844404084,
-    0x2004208a004208,
-    0x10190041080202,
-    0x108060845042010,
-    0x581104180800210,
-    0x2112080446200010,
-    0x1080820820060210,
-    0x3c0808410220200,
-    0x4050404440404,
-    0x21001420088,
-    0x24d0080801082102,
-    0x1020a0a020400,
-    0x40308200402,
-    0x4011002100800,
-    0x401484104104005,
-    0x801010402020200,
-    0x400210c3880100,
-    0x404022024108200,
-    0x810018200204102,
-    0x4002801a02003,
-    0x85040820080400,
-    0x810102c808880400,
-    0xe900410884800,
-    0x8002020480840102,
-    0x220200865090201,
-    0x2010100a02021202,
-    0x152048408022401,
-    0x20080002081110,
-    0x4001001021004000,
-    0x800040400a011002,
-    0xe4004081011002,
-    0x1c004001012080,
-    0x8004200962a00220,
-    0x8422100208500202,
-    0x2000402200300c08,
-    0x8646020080080080,
-    0x80020a0200100808,
-    0x2010004880111000,
-    0x623000a080011400,
-    0x42008c0340209202,
-    0x209188240001000,
-    0x400408a884001800,
-    0x110400a6080400,
-    0x1840060a44020800,
-    0x90080104000041,
-    0x201011000808101,
-    0x1a2208080504f080,
-    0x8012020600211212,
-    0x500861011240000,
-    0x180806108200800,
-    0x4000020e01040044,
-    0x300000261044000a,
-    0x802241102020002,
-    0x20906061210001,
-    0x5a84841004010310,
-    0x4010801011c04,
-    0xa010109502200,
-    0x4a02012000,
-    0x500201010098b028,
-    0x8040002811040900,
-    0x28000010020204,
-    0x6000020202d0240,
-    0x8918844842082200,
-    0x4010011029020020,
-];
-pub const ROOK_SHIFT_AMOUNT: [u64; 64] = [
-    12, 11, 11, 11, 11, 11, 11, 12, 11, 10, 10, 10, 10, 10, 10, 11, 11, 10, 10, 10, 10, 10, 10, 11, 11, 10, 10, 10, 10, 10, 10, 11, 11, 10, 10, 10, 10, 10, 10, 11, 11, 10, 10, 10, 10, 10, 10, 11, 11,
-    10, 10, 10, 10, 10, 10, 11, 12, 11, 11, 11, 11, 11, 11, 12,
-];
-pub const BISHOP_SHIFT_AMOUNT: [u64; 64] = [
-    6, 5, 5, 5, 5, 5, 5, 6, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 7, 7, 7, 7, 5, 5, 5, 5, 7, 9, 9, 7, 5, 5, 5, 5, 7, 9, 9, 7, 5, 5, 5, 5, 7, 7, 7, 7, 5, 5, 5, 5, 5, 5, 5, 5, 5, 5, 6, 5, 5, 5, 5, 5, 5, 6,
+
+pub const BISHOP_TABLE_SIZE: usize = 5193;
+pub const BISHOP_MAGICS: [Magic; 64] = [
+    Magic { magic: 0x482b10a0c1104080, shift: 58, offset: 3583 },
+    Magic { magic: 0x4020018320a86000, shift: 59, offset: 3817 },
+    Magic { magic: 0x4008320842003084, shift: 59, offset: 3847 },
+    Magic { magic: 0x0004105200220400, shift: 59, offset: 3879 },
+    Magic { magic: 0x0614042240520004, shift: 59, offset: 3911 },
+    Magic { magic: 0x0cc1c2ca12029112, shift: 59, offset: 3943 },
+    Magic { magic: 0x4411a0c3200a4088, shift: 59, offset: 3973 },
+    Magic { magic: 0x081024280284e000, shift: 58, offset: 3637 },
+    Magic { magic: 0x2004122902084454, shift: 59, offset: 4003 },
+    Magic { magic: 0x10020d019140c202, shift: 59, offset: 4032 },
+    Magic { magic: 0x20104482c8883002, shift: 59, offset: 4062 },
+    Magic { magic: 0x1080140d120a4200, shift: 59, offset: 4093 },
+    Magic { magic: 0x000012021051400c, shift: 59, offset: 4125 },
+    Magic { magic: 0x0240090120920008, shift: 59, offset: 4157 },
+    Magic { magic: 0x10100eb4d0040584, shift: 59, offset: 4189 },
+    Magic { magic: 0x05a200242ae80886, shift: 59, offset: 4220 },
+    Magic { magic: 0x812006c6258200d8, shift: 59, offset: 4251 },
+    Magic { magic: 0x0028100310849230, shift: 59, offset: 4282 },
+    Magic { magic: 0x00040408002c1606, shift: 57, offset: 2048 },
+    Magic { magic: 0x0004200802002402, shift: 57, offset: 2175 },
+    Magic { magic: 0x848d000090401000, shift: 57, offset: 2303 },
+    Magic { magic: 0x0c00808108014010, shift: 57, offset: 2431 },
+    Magic { magic: 0x001122c201903400, shift: 59, offset: 4313 },
+    Magic { magic: 0xa832020104821100, shift: 59, offset: 4345 },
+    Magic { magic: 0x2003210040080200, shift: 59, offset: 4377 },
+    Magic { magic: 0x0088445028110822, shift: 59, offset: 4409 },
+    Magic { magic: 0x0020480410002044, shift: 57, offset: 2559 },
+    Magic { magic: 0x800c01005d490100, shift: 55, offset: 0 },
+    Magic { magic: 0x1028840082020200, shift: 55, offset: 512 },
+    Magic { magic: 0x0011004002005003, shift: 57, offset: 2687 },
+    Magic { magic: 0x000208a004040100, shift: 59, offset: 4441 },
+    Magic { magic: 0x0800821214804400, shift: 59, offset: 4473 },
+    Magic { magic: 0x4964440400202020, shift: 59, offset: 4505 },
+    Magic { magic: 0x1142101c60700101, shift: 59, offset: 4537 },
+    Magic { magic: 0x1080403000280040, shift: 57, offset: 2815 },
+    Magic { magic: 0x4020020080080080, shift: 55, offset: 1024 },
+    Magic { magic: 0x0001080200602200, shift: 55, offset: 1536 },
+    Magic { magic: 0x0008100d00202081, shift: 57, offset: 2943 },
+    Magic { magic: 0x0008210040040200, shift: 59, offset: 4569 },
+    Magic { magic: 0x0c0208820a202211, shift: 59, offset: 4601 },
+    Magic { magic: 0x00020951c4804000, shift: 59, offset: 4633 },
+    Magic { magic: 0x081a6c7002828800, shift: 59, offset: 4664 },
+    Magic { magic: 0x0248104030110800, shift: 57, offset: 3071 },
+    Magic { magic: 0x4000044200800800, shift: 57, offset: 3199 },
+    Magic { magic: 0x000840010a000903, shift: 57, offset: 3327 },
+    Magic { magic: 0x0001011101020600, shift: 57, offset: 3455 },
+    Magic { magic: 0x808c02f180610400, shift: 59, offset: 4695 },
+    Magic { magic: 0x1450014501014021, shift: 59, offset: 4725 },
+    Magic { magic: 0x0432280208040840, shift: 59, offset: 4757 },
+    Magic { magic: 0x0081250110102002, shift: 59, offset: 4789 },
+    Magic { magic: 0x0010811240d0a080, shift: 59, offset: 4821 },
+    Magic { magic: 0x0000000842020002, shift: 59, offset: 4852 },
+    Magic { magic: 0x0009029833041022, shift: 59, offset: 4884 },
+    Magic { magic: 0x0054200401421000, shift: 59, offset: 4916 },
+    Magic { magic: 0x000883a405225002, shift: 59, offset: 4948 },
+    Magic { magic: 0x0004084800409100, shift: 59, offset: 4976 },
+    Magic { magic: 0x40082a0801860310, shift: 58, offset: 3699 },
+    Magic { magic: 0x8b46010065142000, shift: 59, offset: 5008 },
+    Magic { magic: 0x044001411240d080, shift: 59, offset: 5040 },
+    Magic { magic: 0x80104c1008840409, shift: 59, offset: 5071 },
+    Magic { magic: 0x0008305011421200, shift: 59, offset: 5103 },
+    Magic { magic: 0x8001020408100106, shift: 59, offset: 5135 },
+    Magic { magic: 0x1000070802244a5e, shift: 59, offset: 5167 },
+    Magic { magic: 0x428c180453006019, shift: 58, offset: 3761 },
 ];
@@ -1,4 +1,5 @@
 pub mod attack_tables;
+pub mod magic_finder;
 pub mod magic_numbers;
 pub mod move_generation;
 pub use move_generation::*;
@@ -80,6 +80,22 @@ pub fn perft(fen: &str, depth: u32) -> PerftResult {
     result
 }
 
+// Nodes below each legal move, sorted by move, for comparing with another move generator
+pub fn divide(board: &mut Board, depth: u32) -> Vec<(Move, u64)> {
+    let mut result = PerftResult::default();
+    let mut counts: Vec<(Move, u64)> = generate_moves(board)
+        .into_iter()
+        .map(|mov| {
+            board.make_move(mov);
+            let nodes = search(&mut result, depth.saturating_sub(1), mov, board);
+            board.unmake_move(mov);
+            (mov, nodes)
+        })
+        .collect();
+    counts.sort_by_key(|(mov, _)| mov.to_string());
+    counts
+}
+
 fn search(result: &mut PerftResult, depth: u32, prev_mov: Move, board: &mut Board) -> u64 {
     if depth == 0 {
         result.nodes += 1;
@@ -208,6 +224,15 @@ mod tests {
         println!("Test took {} milliseconds", elapsed.as_millis());
     }
     #[test]
+    fn divides_nodes_by_move() {
+        let mut board = Board::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        let counts = divide(&mut board, 2);
+        assert_eq!(counts.len(), 48);
+        assert_eq!(counts.iter().map(|(_, nodes)| nodes).sum::<u64>(), 2039);
+        assert_eq!(counts[0].0.to_string(), "a1b1");
+        assert!(counts.contains(&(board.parse_uci_move("e1g1").unwrap(), 43)));
+    }
+    #[test]
     fn test_perft2() {
         let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
         assert_eq!(perft(fen, 1).nodes, 48);
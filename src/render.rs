@@ -0,0 +1,279 @@
+use ab_glyph::{FontRef, PxScale};
+use image::{imageops, Rgba, RgbaImage};
+use imageproc::drawing::{draw_filled_rect_mut, draw_polygon_mut, draw_text_mut, text_size, Blend};
+use imageproc::point::Point;
+use imageproc::rect::Rect;
+
+use crate::board::piece::{Piece, PieceType};
+use crate::board::piece_move::Move;
+use crate::board::{Board, Side};
+use crate::search::SearchResult;
+
+const SQUARE_SIZE: u32 = 200; // Of the board sprite, the pieces are 150 pixels
+const PIECE_SIZE: u32 = 150;
+const COORDINATE_SIZE: f32 = 36.0;
+const LIGHT_SQUARE: [u8; 3] = [238, 238, 211];
+const DARK_SQUARE: [u8; 3] = [115, 149, 82];
+const LAST_MOVE_COLOR: [u8; 4] = [255, 255, 51, 110];
+const CHECK_COLOR: [u8; 4] = [220, 30, 30, 150];
+const ARROW_COLOR: [u8; 4] = [230, 120, 20, 200];
+const ARROW_FADE: f32 = 0.7; // Opacity of each arrow relative to the one before
+
+static BOARD_SPRITE: &[u8] = include_bytes!("../assets/board.png");
+static FONT: &[u8] = include_bytes!("../assets/OpenSans-Regular.ttf");
+
+fn piece_sprite(piece: Piece) -> &'static [u8] {
+    match (piece.side(), piece.piece_type()) {
+        (Side::White, PieceType::Pawn) => include_bytes!("../assets/wp.png"),
+        (Side::White, PieceType::Knight) => include_bytes!("../assets/wn.png"),
+        (Side::White, PieceType::Bishop) => include_bytes!("../assets/wb.png"),
+        (Side::White, PieceType::Rook) => include_bytes!("../assets/wr.png"),
+        (Side::White, PieceType::Queen) => include_bytes!("../assets/wq.png"),
+        (Side::White, PieceType::King) => include_bytes!("../assets/wk.png"),
+        (Side::Black, PieceType::Pawn) => include_bytes!("../assets/bp.png"),
+        (Side::Black, PieceType::Knight) => include_bytes!("../assets/bn.png"),
+        (Side::Black, PieceType::Bishop) => include_bytes!("../assets/bb.png"),
+        (Side::Black, PieceType::Rook) => include_bytes!("../assets/br.png"),
+        (Side::Black, PieceType::Queen) => include_bytes!("../assets/bq.png"),
+        (Side::Black, PieceType::King) => include_bytes!("../assets/bk.png"),
+    }
+}
+
+/// What is drawn besides the pieces.
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    pub flipped: bool, // Black at the bottom
+    pub coordinates: bool,
+    pub last_move: Option<Move>,
+    pub arrows: Vec<Move>, // Each fainter than the one before, like the moves of a PV
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self { flipped: false, coordinates: true, last_move: None, arrows: Vec::new() }
+    }
+}
+
+impl RenderOptions {
+    // Arrows for the first moves of the principal variation
+    pub fn with_pv(mut self, result: &SearchResult, moves: usize) -> Self {
+        self.arrows = result.pv.iter().take(moves).copied().collect();
+        self
+    }
+}
+
+// Top left corner of a square in pixels
+fn square_origin(square: usize, flipped: bool) -> (u32, u32) {
+    let (row, file) = if flipped { (7 - square / 8, 7 - square % 8) } else { (square / 8, square % 8) };
+    (file as u32 * SQUARE_SIZE, row as u32 * SQUARE_SIZE)
+}
+
+fn square_center(square: usize, flipped: bool) -> (f32, f32) {
+    let (x, y) = square_origin(square, flipped);
+    ((x + SQUARE_SIZE / 2) as f32, (y + SQUARE_SIZE / 2) as f32)
+}
+
+fn is_light(square: usize) -> bool {
+    (square / 8 + square % 8).is_multiple_of(2)
+}
+
+// Squares tinted for the last move and a king in check
+fn highlights(board: &Board, options: &RenderOptions) -> Vec<(usize, [u8; 4])> {
+    let mut highlights = Vec::new();
+    if let Some(mov) = options.last_move {
+        highlights.push((mov.from(), LAST_MOVE_COLOR));
+        highlights.push((mov.to(), LAST_MOVE_COLOR));
+    }
+    if board.in_check() {
+        highlights.push((board.piece_squares[Piece::new(PieceType::King, board.side)].lsb(), CHECK_COLOR));
+    }
+    highlights
+}
+
+// Labels of the files along the bottom edge and of the ranks along the left edge, with the square
+// they are drawn on
+fn coordinates(flipped: bool) -> Vec<(usize, String, bool)> {
+    let mut labels = Vec::new();
+    for index in 0..8 {
+        let (bottom, left) = if flipped { (7 - index, index * 8 + 7) } else { (56 + index, index * 8) };
+        labels.push((bottom, ((b'a' + (bottom % 8) as u8) as char).to_string(), true));
+        labels.push((left, (8 - left / 8).to_string(), false));
+    }
+    labels
+}
+
+// Outline of an arrow from the center of one square to the center of another
+fn arrow_polygon(mov: Move, flipped: bool) -> Vec<(f32, f32)> {
+    let (from, to) = (square_center(mov.from(), flipped), square_center(mov.to(), flipped));
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    let length = (dx * dx + dy * dy).sqrt();
+    let (ux, uy) = (dx / length, dy / length);
+    let (shaft, head_width, head_length) = (SQUARE_SIZE as f32 * 0.09, SQUARE_SIZE as f32 * 0.22, SQUARE_SIZE as f32 * 0.4);
+    let base = (to.0 - ux * head_length, to.1 - uy * head_length);
+    let side = |point: (f32, f32), width: f32| (point.0 - uy * width, point.1 + ux * width);
+    vec![side(from, shaft), side(base, shaft), side(base, head_width), to, side(base, -head_width), side(base, -shaft), side(from, -shaft)]
+}
+
+fn arrow_alpha(index: usize) -> u8 {
+    (ARROW_COLOR[3] as f32 * ARROW_FADE.powi(index as i32)) as u8
+}
+
+pub fn render_png(board: &Board, options: &RenderOptions) -> RgbaImage {
+    let image = image::load_from_memory(BOARD_SPRITE).expect("invalid board sprite").to_rgba8();
+    let mut canvas = Blend(image);
+    for (square, color) in highlights(board, options) {
+        let (x, y) = square_origin(square, options.flipped);
+        draw_filled_rect_mut(&mut canvas, Rect::at(x as i32, y as i32).of_size(SQUARE_SIZE, SQUARE_SIZE), Rgba(color));
+    }
+
+    if options.coordinates {
+        let font = FontRef::try_from_slice(FONT).expect("invalid font");
+        let scale = PxScale::from(COORDINATE_SIZE);
+        for (square, label, is_file) in coordinates(options.flipped) {
+            let (x, y) = square_origin(square, options.flipped);
+            let [r, g, b] = if is_light(square) { DARK_SQUARE } else { LIGHT_SQUARE };
+            let (width, height) = text_size(scale, &font, &label);
+            let (x, y) = if is_file { (x + SQUARE_SIZE - width - 8, y + SQUARE_SIZE - height - 24) } else { (x + 8, y + 6) };
+            draw_text_mut(&mut canvas, Rgba([r, g, b, 255]), x as i32, y as i32, scale, &font, &label);
+        }
+    }
+
+    let mut image = canvas.0;
+    for (square, piece) in board.squares.iter().enumerate() {
+        if let Some(piece) = piece {
+            let sprite = image::load_from_memory(piece_sprite(*piece)).expect("invalid piece sprite").to_rgba8();
+            let (x, y) = square_origin(square, options.flipped);
+            let offset = (SQUARE_SIZE - PIECE_SIZE) / 2;
+            imageops::overlay(&mut image, &sprite, (x + offset) as i64, (y + offset) as i64);
+        }
+    }
+
+    let mut canvas = Blend(image);
+    for (index, &mov) in options.arrows.iter().enumerate() {
+        let points: Vec<Point<i32>> = arrow_polygon(mov, options.flipped).iter().map(|&(x, y)| Point::new(x.round() as i32, y.round() as i32)).collect();
+        let [r, g, b, _] = ARROW_COLOR;
+        draw_polygon_mut(&mut canvas, &points, Rgba([r, g, b, arrow_alpha(index)]));
+    }
+    canvas.0
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let bits = (chunk[0] as u32) << 16 | (*chunk.get(1).unwrap_or(&0) as u32) << 8 | *chunk.get(2).unwrap_or(&0) as u32;
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(ALPHABET[(bits >> (18 - 6 * index) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn svg_color(color: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+// Same diagram as the PNG, with the piece sprites embedded once each
+pub fn render_svg(board: &Board, options: &RenderOptions) -> String {
+    let size = SQUARE_SIZE * 8;
+    let mut svg = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {size} {size}\" width=\"{}\" height=\"{}\">\n", size / 2, size / 2);
+
+    svg.push_str("<defs>\n");
+    let mut pieces: Vec<Piece> = board.squares.iter().flatten().copied().collect();
+    pieces.sort_by_key(|piece| *piece as u8);
+    pieces.dedup();
+    for piece in pieces {
+        svg.push_str(&format!("<image id=\"{}\" width=\"{PIECE_SIZE}\" height=\"{PIECE_SIZE}\" href=\"data:image/png;base64,{}\"/>\n", piece_id(piece), base64(piece_sprite(piece))));
+    }
+    svg.push_str("</defs>\n");
+
+    for square in 0..64 {
+        let (x, y) = square_origin(square, options.flipped);
+        let color = if is_light(square) { LIGHT_SQUARE } else { DARK_SQUARE };
+        svg.push_str(&format!("<rect x=\"{x}\" y=\"{y}\" width=\"{SQUARE_SIZE}\" height=\"{SQUARE_SIZE}\" fill=\"{}\"/>\n", svg_color(color)));
+    }
+    for (square, [r, g, b, a]) in highlights(board, options) {
+        let (x, y) = square_origin(square, options.flipped);
+        svg.push_str(&format!("<rect x=\"{x}\" y=\"{y}\" width=\"{SQUARE_SIZE}\" height=\"{SQUARE_SIZE}\" fill=\"{}\" fill-opacity=\"{:.2}\"/>\n", svg_color([r, g, b]), a as f32 / 255.0));
+    }
+
+    if options.coordinates {
+        for (square, label, is_file) in coordinates(options.flipped) {
+            let (x, y) = square_origin(square, options.flipped);
+            let color = if is_light(square) { DARK_SQUARE } else { LIGHT_SQUARE };
+            let (x, y, anchor) = if is_file { (x + SQUARE_SIZE - 8, y + SQUARE_SIZE - 12, "end") } else { (x + 8, y + 40, "start") };
+            svg.push_str(&format!(
+                "<text x=\"{x}\" y=\"{y}\" text-anchor=\"{anchor}\" font-family=\"Open Sans, sans-serif\" font-size=\"{COORDINATE_SIZE}\" fill=\"{}\">{label}</text>\n",
+                svg_color(color)
+            ));
+        }
+    }
+
+    for (square, piece) in board.squares.iter().enumerate() {
+        if let Some(piece) = piece {
+            let (x, y) = square_origin(square, options.flipped);
+            let offset = (SQUARE_SIZE - PIECE_SIZE) / 2;
+            svg.push_str(&format!("<use href=\"#{}\" x=\"{}\" y=\"{}\"/>\n", piece_id(*piece), x + offset, y + offset));
+        }
+    }
+
+    for (index, &mov) in options.arrows.iter().enumerate() {
+        let points: Vec<String> = arrow_polygon(mov, options.flipped).iter().map(|(x, y)| format!("{:.1},{:.1}", x, y)).collect();
+        let [r, g, b, _] = ARROW_COLOR;
+        svg.push_str(&format!("<polygon points=\"{}\" fill=\"{}\" fill-opacity=\"{:.2}\"/>\n", points.join(" "), svg_color([r, g, b]), arrow_alpha(index) as f32 / 255.0));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn piece_id(piece: Piece) -> String {
+    let side = if piece.side() == Side::White { 'w' } else { 'b' };
+    format!("{}{}", side, piece.to_char().to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::move_generation::generate_moves;
+
+    #[test]
+    fn draws_highlights_and_arrows() {
+        let mut board = Board::from_fen("rnbqkbnr/ppp2ppp/8/1B1pp3/4P3/8/PPPP1PPP/RNBQK1NR b KQkq - 1 3");
+        let e8 = 4;
+        assert!(board.in_check());
+        let moves: Vec<Move> = generate_moves(&board).into_iter().take(2).collect();
+        let options = RenderOptions { last_move: moves.first().copied(), arrows: moves.clone(), ..Default::default() };
+
+        let image = render_png(&board, &options);
+        assert_eq!(image.dimensions(), (1600, 1600));
+        let is_red = |image: &RgbaImage, flipped: bool| {
+            let (x, y) = square_origin(e8, flipped);
+            let corner = image.get_pixel(x + 2, y + 2);
+            corner[0] > corner[1] + 50 && corner[0] > corner[2] + 50
+        };
+        assert!(is_red(&image, false));
+        assert!(is_red(&render_png(&board, &RenderOptions { flipped: true, ..options.clone() }), true));
+
+        let svg = render_svg(&board, &options);
+        assert_eq!(svg.matches("<use ").count(), 32);
+        assert_eq!(svg.matches("<image ").count(), 12);
+        assert_eq!(svg.matches("<polygon ").count(), 2);
+        assert_eq!(svg.matches("<text ").count(), 16);
+        assert_eq!(svg.matches("fill-opacity").count(), 5);
+
+        board.make_move(moves[0]);
+        assert!(!render_svg(&board, &RenderOptions { coordinates: false, ..Default::default() }).contains("<text"));
+    }
+
+    #[test]
+    fn encodes_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+}
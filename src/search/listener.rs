@@ -0,0 +1,180 @@
+use std::time::Duration;
+
+use crate::board::piece_move::Move;
+use crate::search::search::{SearchResult, MATE_THRESHOLD, MAX_EVAL};
+use crate::search::transposition_table::Bound;
+
+/// Snapshot of the search sent to listeners after an iteration or an aspiration window failure.
+#[derive(Clone, Debug)]
+pub struct SearchInfo {
+    pub depth: u32,
+    pub seldepth: u32,
+    pub eval: i32,
+    pub bound: Bound,
+    pub time: Duration,
+    pub nodes: u32,
+    pub hashfull: usize,
+    pub tb_hits: u32,
+    pub pv: Vec<Move>,
+    pub transpositions: u32,
+    pub transpositions_exact: u32,
+    pub transpositions_lower: u32,
+    pub transpositions_upper: u32,
+}
+
+impl SearchInfo {
+    pub fn nps(&self) -> u32 {
+        ((self.nodes as f64 / self.time.as_nanos() as f64) * 1e9) as u32
+    }
+    // Scores within MAX_DEPTH of MAX_EVAL encode a forced mate, given here in moves rather than plies.
+    // Tablebase wins score below that band and are reported in centipawns.
+    pub fn mate_in(&self) -> Option<i32> {
+        if self.eval >= MATE_THRESHOLD {
+            Some((MAX_EVAL - self.eval + 1) / 2)
+        } else if self.eval <= -MATE_THRESHOLD {
+            Some(-(MAX_EVAL + self.eval) / 2)
+        } else {
+            None
+        }
+    }
+}
+
+/// Receives progress from a running `Search`. Every callback defaults to doing nothing, so
+/// implementations only override the events they care about.
+pub trait SearchListener: Send {
+    fn on_iteration_complete(&mut self, _info: &SearchInfo) {}
+    fn on_aspiration_fail(&mut self, _info: &SearchInfo) {}
+    fn on_best_move(&mut self, _depth: u32, _mov: Move, _eval: i32) {}
+    fn on_current_move(&mut self, _depth: u32, _mov: Move, _move_number: usize) {}
+    fn on_search_finished(&mut self, _result: &SearchResult) {}
+}
+
+/// Ignores every event, used when searching without any output.
+pub struct SilentListener;
+
+impl SearchListener for SilentListener {}
+
+/// Writes events to stdout as UCI `info` and `bestmove` lines.
+#[derive(Default)]
+pub struct UciListener {
+    pub debug: bool, // Report internal counters as info strings
+}
+
+impl UciListener {
+    fn print_info(&self, info: &SearchInfo) {
+        let score = match info.mate_in() {
+            Some(moves) => format!("mate {}", moves),
+            None => format!("cp {}", info.eval),
+        };
+        let bound = match info.bound {
+            Bound::Exact => "",
+            Bound::Lower => " lowerbound",
+            Bound::Upper => " upperbound",
+        };
+        print!(
+            "info depth {} seldepth {} score {}{} time {} nodes {} nps {} hashfull {} tbhits {}",
+            info.depth,
+            info.seldepth,
+            score,
+            bound,
+            info.time.as_millis(),
+            info.nodes,
+            info.nps(),
+            info.hashfull,
+            info.tb_hits
+        );
+        if !info.pv.is_empty() {
+            let pv: Vec<String> = info.pv.iter().map(|mov| mov.to_string()).collect();
+            print!(" pv {}", pv.join(" "));
+        }
+        println!();
+        if self.debug {
+            println!("info string tt {} tt_exact {} tt_lower {} tt_upper {}", info.transpositions, info.transpositions_exact, info.transpositions_lower, info.transpositions_upper);
+        }
+    }
+}
+
+impl SearchListener for UciListener {
+    fn on_iteration_complete(&mut self, info: &SearchInfo) {
+        self.print_info(info);
+    }
+    fn on_aspiration_fail(&mut self, info: &SearchInfo) {
+        self.print_info(info);
+    }
+    fn on_current_move(&mut self, depth: u32, mov: Move, move_number: usize) {
+        println!("info depth {} currmove {} currmovenumber {}", depth, mov, move_number);
+    }
+    fn on_search_finished(&mut self, result: &SearchResult) {
+        if let Some(mov) = result.pv.first() {
+            println!("bestmove {}", mov);
+        } else {
+            println!("bestmove (none)");
+        }
+    }
+}
+
+/// Writes iterations to stdout as CECP thinking output, `ply score time nodes pv`, while posting
+/// is on. Moves are played by the front end, which owns the game.
+#[derive(Default)]
+pub struct XboardListener {
+    pub post: bool,
+}
+
+impl XboardListener {
+    // Centipawns, with mates given as 100000 plus the number of moves like most CECP engines do
+    pub fn score(info: &SearchInfo) -> i32 {
+        match info.mate_in() {
+            Some(moves) if moves > 0 => 100000 + moves,
+            Some(moves) => -100000 + moves,
+            None => info.eval,
+        }
+    }
+}
+
+impl SearchListener for XboardListener {
+    fn on_iteration_complete(&mut self, info: &SearchInfo) {
+        if !self.post {
+            return;
+        }
+        let pv: Vec<String> = info.pv.iter().map(|mov| mov.to_string()).collect();
+        println!("{} {} {} {} {}", info.depth, Self::score(info), info.time.as_millis() / 10, info.nodes, pv.join(" "));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::search::TB_WIN;
+
+    fn info_with_eval(eval: i32) -> SearchInfo {
+        SearchInfo {
+            depth: 1,
+            seldepth: 1,
+            eval,
+            bound: Bound::Exact,
+            time: Duration::ZERO,
+            nodes: 0,
+            hashfull: 0,
+            tb_hits: 0,
+            pv: Vec::new(),
+            transpositions: 0,
+            transpositions_exact: 0,
+            transpositions_lower: 0,
+            transpositions_upper: 0,
+        }
+    }
+
+    #[test]
+    fn converts_mate_scores_to_moves() {
+        assert_eq!(info_with_eval(MAX_EVAL - 1).mate_in(), Some(1));
+        assert_eq!(info_with_eval(MAX_EVAL - 3).mate_in(), Some(2));
+        assert_eq!(info_with_eval(-MAX_EVAL + 2).mate_in(), Some(-1));
+        assert_eq!(info_with_eval(150).mate_in(), None);
+        assert_eq!(info_with_eval(TB_WIN - 4).mate_in(), None);
+        assert_eq!(info_with_eval(-TB_WIN + 3).mate_in(), None);
+        assert_eq!(XboardListener::score(&info_with_eval(MAX_EVAL - 3)), 100002);
+        assert_eq!(XboardListener::score(&info_with_eval(-MAX_EVAL + 2)), -100001);
+        assert_eq!(XboardListener::score(&info_with_eval(-40)), -40);
+        assert_eq!(XboardListener::score(&info_with_eval(TB_WIN - 4)), TB_WIN - 4);
+    }
+}
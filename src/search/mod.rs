@@ -1,5 +1,7 @@
 pub mod book_moves;
+pub mod listener;
 pub mod search;
 pub mod transposition_table;
 pub mod move_ordering;
+pub mod time_manager;
 pub use search::*;
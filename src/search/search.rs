@@ -1,32 +1,51 @@
-use pyrrhic_rs::DtzProbeResult;
+use pyrrhic_rs::{DtzProbeResult, WdlProbeResult};
 
 use crate::board::bitboard::Bitboard;
 use crate::board::piece::{Piece, PieceType};
 use crate::board::piece_move::{Move, MoveType};
 use crate::board::utils::flip_rank;
 use crate::board::{Board, Side};
-use crate::evaluation::evaluate;
+use crate::evaluation::{evaluate, kpk};
 use crate::move_generation::generate_moves;
 use crate::search::book_moves::get_book_move;
-use crate::search::transposition_table::{Bound, TranspositionEntry};
-use core::{hash, simd};
+use crate::search::transposition_table::{mb_to_count, Bound, TranspositionEntry, TranspositionTable};
 use std::char::MAX;
 use std::cmp::Ordering;
 use std::i32::{self};
 use std::ops::Deref;
-use std::simd::num::SimdUint;
-use std::simd::u64x8;
-use std::sync::atomic::AtomicBool;
-use std::sync::Arc;
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::{Arc, Condvar, LazyLock, Mutex};
 use std::time::{Duration, Instant};
 
+use super::listener::{SearchInfo, SearchListener, UciListener};
 use super::move_ordering::order_moves;
-use super::transposition_table;
+use super::time_manager::TimeManager;
 
 pub const MAX_DEPTH: usize = 100;
 pub const KILLER_MOVE_SLOTS: usize = 3;
 
-const MAX_EVAL: i32 = 20000;
+pub const MAX_EVAL: i32 = 20000;
+pub const MATE_THRESHOLD: i32 = MAX_EVAL - MAX_DEPTH as i32;
+// Tablebase wins are scored TB_WIN - ply, below every mate score since the distance to mate isn't known
+pub const TB_WIN: i32 = MATE_THRESHOLD - 1;
+pub const TB_WIN_THRESHOLD: i32 = TB_WIN - MAX_DEPTH as i32;
+
+// Null move pruning parameters
+const NULL_MOVE_MIN_DEPTH: u32 = 3;
+const NULL_MOVE_BASE_REDUCTION: u32 = 2;
+const NULL_MOVE_DEPTH_DIVISOR: u32 = 3;
+const NULL_MOVE_EVAL_DIVISOR: i32 = 200;
+const NULL_MOVE_MAX_EVAL_REDUCTION: i32 = 3;
+const NULL_MOVE_VERIFICATION_DEPTH: u32 = 12;
+
+// Internal iterative reductions
+const IIR_MIN_DEPTH: u32 = 4;
+
+// Milliseconds into the search before the root move being searched is reported
+const CURRMOVE_DELAY: u128 = 1000;
+
+// pyrrhic only allows the tablebases to be initialized once, so every search shares this handle
+pub(crate) static SYZYGY: LazyLock<pyrrhic_rs::TableBases<Board>> = LazyLock::new(|| pyrrhic_rs::TableBases::<Board>::new("./syzygy/tb345").unwrap());
 
 // Transposition Table
 #[cfg(feature = "tt")]
@@ -103,25 +122,67 @@ pub enum NodeType {
     NonPV,
 }
 
+/// Stops a running search. The search polls it, and searches that may not finish before being
+/// stopped block on it without spinning.
+#[derive(Default)]
+pub struct StopSignal {
+    stopped: AtomicBool,
+    lock: Mutex<()>, // Held while stopping, so a waiting search can't miss the notification
+    condvar: Condvar,
+}
+
+impl StopSignal {
+    pub fn stop(&self) {
+        let _guard = self.lock.lock().unwrap();
+        self.stopped.store(true, atomic::Ordering::SeqCst);
+        self.condvar.notify_all();
+    }
+    pub fn reset(&self) {
+        self.stopped.store(false, atomic::Ordering::SeqCst);
+    }
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(atomic::Ordering::Relaxed)
+    }
+    // Blocks until the signal is stopped
+    pub fn wait(&self) {
+        let guard = self.lock.lock().unwrap();
+        let _guard = self.condvar.wait_while(guard, |_| !self.stopped.load(atomic::Ordering::SeqCst)).unwrap();
+    }
+}
+
 pub struct Search {
     pub params: SearchParams,
     pub result: SearchResult,
-    pub max_time: u128,
+    pub time_manager: TimeManager,
+    pub root_move_nodes: [[u32; 64]; 64], // Nodes spent below each root move, indexed by from and to square
     pub killer_moves: [[Option<Move>; KILLER_MOVE_SLOTS]; MAX_DEPTH],
     pub pv_table: [[Option<Move>; MAX_DEPTH]; MAX_DEPTH], // Initialize PV table
     pub pv_lengths: [usize; MAX_DEPTH],
     //pub pv: Vec<Move>,
-    pub should_quit: Arc<AtomicBool>, // Shared atomic flag
+    pub should_quit: Arc<StopSignal>,
+    pub transposition_table: Arc<TranspositionTable>, // Shared with other searches by cloning the Arc
     pub root_ply: u32,
     pub syzygy: pyrrhic_rs::TableBases<Board>,
     pub start_time: Instant,
     pub previous_static_eval: i32,
     pub history: [[[u32; 64]; 64]; 2],
+    pub null_move_min_ply: u32, // Null moves are disabled below this ply during verification searches
+    pub listener: Box<dyn SearchListener>,
 }
 
 impl Search {
     pub fn search(&mut self, search_params: SearchParams, board: &mut Board) -> SearchResult {
-        self.should_quit.store(false, std::sync::atomic::Ordering::SeqCst);
+        let result = self.run(search_params, board);
+        // UCI only allows the best move after a stop when searching infinitely, also when it came
+        // from the book or the tablebases or the search ran out of depth
+        if search_params.search_mode == SearchMode::Infinite && search_params.depth.is_none() && search_params.nodes.is_none() {
+            self.should_quit.wait();
+        }
+        self.listener.on_search_finished(&result);
+        result
+    }
+    fn run(&mut self, search_params: SearchParams, board: &mut Board) -> SearchResult {
+        // The flag is cleared by whoever starts the search, so a stop sent right after is not lost
         self.result = SearchResult::default();
         self.pv_table = [[None; MAX_DEPTH]; MAX_DEPTH];
         self.pv_lengths = [0; MAX_DEPTH];
@@ -136,11 +197,11 @@ impl Search {
 
         self.params = search_params;
         self.killer_moves = [[None; KILLER_MOVE_SLOTS]; MAX_DEPTH];
+        self.null_move_min_ply = 0;
         self.start_time = Instant::now();
         self.root_ply = board.ply;
 
-        if USE_SYZYGY && board.occupied_squares.count_ones() <= 5 {
-            let result = self.probe_syzygy_root(board);
+        if let Some(result) = self.probe_syzygy_root(board) {
             match result.root {
                 pyrrhic_rs::DtzProbeValue::Stalemate => return self.result.clone(),
                 pyrrhic_rs::DtzProbeValue::Checkmate => return self.result.clone(),
@@ -160,20 +221,20 @@ impl Search {
                         }
                     };
                     let mov = Move::new(flip_rank(dtz_result.from_square as usize), flip_rank(dtz_result.to_square as usize), move_type);
-                    self.result.pv.clear();
-                    self.result.pv.push(mov);
-                    Search::print_info(&self.result);
+                    self.pv_table[0][0] = Some(mov);
+                    self.pv_lengths[0] = 1;
+                    self.result.pv = self.extract_pv();
+                    self.result.tb_hits += 1;
+                    self.result.highest_eval = wdl_eval(dtz_result.wdl, 0);
+                    let info = self.search_info(self.result.depth_reached, self.result.highest_eval, Bound::Exact);
+                    self.listener.on_iteration_complete(&info);
                     return self.result.clone();
                 }
             }
         }
 
-        self.max_time = match search_params.search_mode {
-            SearchMode::Infinite => u128::max_value(),
-            SearchMode::MoveTime => search_params.move_time,
-            SearchMode::Clock => self.calculate_time(board),
-        };
-        //self.max_time = 3000;
+        self.time_manager = TimeManager::new(&search_params, board);
+        self.root_move_nodes = [[0; 64]; 64];
 
         // Modify search logic based on USE_ITERATIVE_DEEPENING constant
         if USE_ITERATIVE_DEEPENING {
@@ -193,13 +254,22 @@ impl Search {
                     // Iterative deepening with gradually expanding windows
                     loop {
                         eval = self.pvs::<{ NodeType::Root as u8 }, false>(board, depth, alpha, beta, 0);
-                        // Handle window failures
+
+                        if self.should_quit(depth) {
+                            break;
+                        }
+
+                        // Handle window failures, reporting the score as a bound since it is not exact
                         if eval <= alpha {
                             // Lower bound failure - expand window downward
+                            let info = self.search_info(depth, eval, Bound::Upper);
+                            self.listener.on_aspiration_fail(&info);
                             window_size = window_size * 2;
                             alpha = i32::max(eval - window_size, -MAX_EVAL);
                         } else if eval >= beta {
                             // Upper bound failure - expand window upward
+                            let info = self.search_info(depth, eval, Bound::Lower);
+                            self.listener.on_aspiration_fail(&info);
                             window_size = window_size * 2;
                             beta = i32::min(eval + window_size, MAX_EVAL);
                         } else {
@@ -224,7 +294,17 @@ impl Search {
                 self.result.depth_reached = depth;
                 self.result.pv = self.extract_pv();
                 self.result.time = self.start_time.elapsed();
-                Search::print_info(&self.result);
+                let info = self.search_info(depth, eval, Bound::Exact);
+                self.listener.on_iteration_complete(&info);
+
+                if let Some(&best_move) = self.result.pv.first() {
+                    let best_move_nodes = self.root_move_nodes[best_move.from()][best_move.to()];
+                    self.time_manager.update(Some(best_move), eval, best_move_nodes, self.result.nodes);
+                }
+                // Another iteration is unlikely to finish before the hard limit
+                if self.time_manager.soft_limit_reached(self.start_time.elapsed().as_millis()) {
+                    break;
+                }
             }
         } else {
             unimplemented!("iterative deepening needs to be enabled");
@@ -235,12 +315,13 @@ impl Search {
             self.result.depth_reached = MAX_DEPTH as u32;
             self.result.pv = self.extract_pv();
             self.result.time = self.start_time.elapsed();
-            Search::print_info(&self.result);
+            let info = self.search_info(MAX_DEPTH as u32, eval, Bound::Exact);
+            self.listener.on_iteration_complete(&info);
         }
 
         self.result.clone()
     }
-    fn pvs<const NODE_TYPE: u8, const IS_NULL: bool>(&mut self, board: &mut Board, depth: u32, mut alpha: i32, mut beta: i32, ply: u32) -> i32 {
+    fn pvs<const NODE_TYPE: u8, const IS_NULL: bool>(&mut self, board: &mut Board, mut depth: u32, mut alpha: i32, mut beta: i32, ply: u32) -> i32 {
         let on_pv = NODE_TYPE != NodeType::NonPV as u8;
         let is_root = NODE_TYPE == NodeType::Root as u8;
 
@@ -273,14 +354,17 @@ impl Search {
         }
 
         self.result.nodes += 1;
+        self.result.seldepth = self.result.seldepth.max(ply);
 
         let mut hash_move = None;
 
         if USE_TT {
-            if let Some(entry) = transposition_table::global_probe(board.zobrist_hash) {
+            if let Some(entry) = self.transposition_table.probe(board.zobrist_hash).filter(|entry| entry.hash == board.zobrist_hash) {
+                // The stored move is useful for ordering even when the entry is too shallow for a cutoff
+                hash_move = Some(entry.best_move);
+
                 //if entry.hash == board.zobrist_hash && entry.depth as u32 >= depth && !is_root && NODE_TYPE == NodeType::NonPV as u8 && !IS_NULL {
-                if entry.hash == board.zobrist_hash && entry.depth as u32 >= depth && !is_root && !IS_NULL {
-                    hash_move = Some(entry.best_move);
+                if entry.depth as u32 >= depth && !is_root && !IS_NULL {
                     self.result.transpositions += 1;
 
                     //match entry.node_type {
@@ -311,8 +395,7 @@ impl Search {
 
         //let hash_move = tt_hit.map(|entry| entry.best_move);
 
-        if USE_SYZYGY && board.occupied_squares.count_ones() <= 5 {
-            let result = self.probe_syzygy_root(board);
+        if let Some(result) = self.probe_syzygy_root(board) {
             match result.root {
                 pyrrhic_rs::DtzProbeValue::Stalemate => return 0,
                 pyrrhic_rs::DtzProbeValue::Checkmate => {
@@ -325,34 +408,73 @@ impl Search {
                 }
                 pyrrhic_rs::DtzProbeValue::Failed => eprintln!("Dtz probe failed at root"),
                 pyrrhic_rs::DtzProbeValue::DtzResult(dtz_result) => {
-                    return match dtz_result.wdl {
-                        pyrrhic_rs::WdlProbeResult::Loss => -MAX_EVAL + ply as i32,
-                        pyrrhic_rs::WdlProbeResult::BlessedLoss => -MAX_EVAL + 10000 + ply as i32,
-                        pyrrhic_rs::WdlProbeResult::Draw => 0,
-                        pyrrhic_rs::WdlProbeResult::CursedWin => MAX_EVAL - 10000 - ply as i32,
-                        pyrrhic_rs::WdlProbeResult::Win => MAX_EVAL - ply as i32,
-                    };
+                    self.result.tb_hits += 1;
+                    return wdl_eval(dtz_result.wdl, ply);
                 }
             }
         }
 
-        //let static_eval = evaluate(board);
-        //let improving = ply >= 2 && static_eval > self.previous_static_eval;
-        //self.previous_static_eval = static_eval;
+        // Drawn king and pawn endings need no search, won ones are still searched to make progress
+        if !is_root && kpk::probe_board(board) == Some(false) {
+            return 0;
+        }
+
+        let in_check = board.in_check();
+        // Null move pruning is unsound in zugzwang, which mostly arises when the side to move only
+        // has pawns left, so those positions are skipped entirely
+        if USE_NULL_MOVE
+            && !IS_NULL
+            && !on_pv
+            && !in_check
+            && depth >= NULL_MOVE_MIN_DEPTH
+            && ply >= self.null_move_min_ply
+            && board.has_non_pawn_material(board.side)
+        {
+            // Only evaluated once the cheaper conditions pass, nothing else here needs it
+            let static_eval = evaluate(board);
+            if static_eval >= beta {
+                // Reduce more at higher depths and when the static evaluation is far above beta
+                let eval_reduction = ((static_eval - beta) / NULL_MOVE_EVAL_DIVISOR).min(NULL_MOVE_MAX_EVAL_REDUCTION) as u32;
+                let reduction = NULL_MOVE_BASE_REDUCTION + depth / NULL_MOVE_DEPTH_DIVISOR + eval_reduction;
+                let null_depth = depth.saturating_sub(1 + reduction);
 
-        if USE_NULL_MOVE {
-            let reduction = 2;
-            if !IS_NULL && !board.in_check() && depth > reduction + 1 {
                 board.make_null_move();
-                let null_move_eval = -self.pvs::<{ NodeType::NonPV as u8 }, true>(board, depth - 1 - reduction, -beta, -beta + 1, ply + 1);
+                let mut null_move_eval = -self.pvs::<{ NodeType::NonPV as u8 }, true>(board, null_depth, -beta, -beta + 1, ply + 1);
                 board.unmake_null_move();
 
+                if self.should_quit(ply) {
+                    return 0;
+                }
+
                 if null_move_eval >= beta {
-                    return null_move_eval; // Beta cutoff, prune this node
+                    // Mate scores found after passing are not proven, so don't return them
+                    if null_move_eval >= MATE_THRESHOLD {
+                        null_move_eval = beta;
+                    }
+
+                    if depth < NULL_MOVE_VERIFICATION_DEPTH || self.null_move_min_ply != 0 {
+                        return null_move_eval; // Beta cutoff, prune this node
+                    }
+
+                    // At high depths verify the cutoff with a reduced search where null moves are
+                    // disabled for the first part of the tree, which catches zugzwang
+                    self.null_move_min_ply = ply + 3 * null_depth / 4;
+                    let verification_eval = self.pvs::<{ NodeType::NonPV as u8 }, false>(board, null_depth, beta - 1, beta, ply);
+                    self.null_move_min_ply = 0;
+
+                    if verification_eval >= beta {
+                        return null_move_eval;
+                    }
                 }
             }
         }
 
+        // Internal iterative reductions: without a hash move the move ordering is likely poor,
+        // so search this node shallower and let the next iteration fill in the TT move
+        if hash_move.is_none() && depth >= IIR_MIN_DEPTH && !is_root {
+            depth -= 1;
+        }
+
         //let futility_margin = 100 * depth as i32;
 
         let mut moves = generate_moves(board);
@@ -380,6 +502,11 @@ impl Search {
         for (i, &mov) in moves.iter().enumerate() {
             // let evading_check = board.in_check();
 
+            if is_root && self.start_time.elapsed().as_millis() > CURRMOVE_DELAY {
+                self.listener.on_current_move(depth, mov, i + 1);
+            }
+
+            let nodes_before = self.result.nodes;
             board.make_move(mov);
 
             let gives_check = board.in_check();
@@ -439,6 +566,10 @@ impl Search {
 
             board.unmake_move(mov);
 
+            if is_root {
+                self.root_move_nodes[mov.from()][mov.to()] += self.result.nodes - nodes_before;
+            }
+
             if self.should_quit(ply) {
                 return 0;
             }
@@ -448,9 +579,10 @@ impl Search {
                 best_move = Some(mov);
 
                 if !USE_ALPHA_BETA || eval > alpha {
-                    //if (is_root) {
-                    //    self.result.best_move = Some(mov);
-                    //}
+                    if is_root && self.result.best_move != Some(mov) {
+                        self.result.best_move = Some(mov);
+                        self.listener.on_best_move(depth, mov, eval);
+                    }
                     if !IS_NULL {
                         self.pv_table[ply as usize][0] = best_move;
 
@@ -484,7 +616,7 @@ impl Search {
 
         if let Some(best_move) = best_move {
             let entry = TranspositionEntry::new(depth as u8, best_eval as i16, best_move, evaluation_bound, board.zobrist_hash);
-            transposition_table::global_store(entry);
+            self.transposition_table.store(entry);
         }
 
         best_eval
@@ -492,6 +624,7 @@ impl Search {
 
     fn quiescence_search(&mut self, board: &mut Board, mut alpha: i32, beta: i32, ply: u32) -> i32 {
         self.result.nodes += 1;
+        self.result.seldepth = self.result.seldepth.max(ply);
 
         if self.should_quit(ply) {
             return 0;
@@ -553,18 +686,12 @@ impl Search {
         alpha
     }
 
-    fn probe_syzygy_root(&mut self, board: &Board) -> DtzProbeResult {
-        let mut bitboards = u64x8::from_array([
-            *board.side_squares[Side::White],
-            *board.side_squares[Side::Black],
-            *(board.piece_squares[Piece::WhiteKing] | board.piece_squares[Piece::BlackKing]),
-            *(board.piece_squares[Piece::WhiteQueen] | board.piece_squares[Piece::BlackQueen]),
-            *(board.piece_squares[Piece::WhiteRook] | board.piece_squares[Piece::BlackRook]),
-            *(board.piece_squares[Piece::WhiteBishop] | board.piece_squares[Piece::BlackBishop]),
-            *(board.piece_squares[Piece::WhiteKnight] | board.piece_squares[Piece::BlackKnight]),
-            *(board.piece_squares[Piece::WhitePawn] | board.piece_squares[Piece::BlackPawn]),
-        ]);
-        bitboards = bitboards.swap_bytes();
+    // None when the tables are missing or can't be probed, in which case the search plays on its own
+    fn probe_syzygy_root(&mut self, board: &Board) -> Option<DtzProbeResult> {
+        if !USE_SYZYGY || board.occupied_squares.count_ones() > 5 {
+            return None;
+        }
+        let bitboards = syzygy_bitboards(board);
         //for bitboard in bitboards.to_array().iter() {
         //    println!("{}", Bitboard(*bitboard));
         //}
@@ -572,8 +699,7 @@ impl Search {
         //println!("halfmove {}", board.state().halfmove_clock as u32 / 2);
         //println!("fen {}", board.fen());
         //println!("ep dadwa: {}", flip_rank(board.state().en_passant_square.unwrap_or(56)) as u32);
-        return self
-            .syzygy
+        self.syzygy
             .probe_root(
                 bitboards[0],
                 bitboards[1],
@@ -587,7 +713,34 @@ impl Search {
                 flip_rank(board.state().en_passant_square.unwrap_or(56)) as u32,
                 board.side.value() == 0,
             )
-            .expect(&format!("Syzygy tablebase probe failed, fen: {}", board.fen()));
+            .ok()
+    }
+    // Win, draw or loss for the side to move, None when the tables can't be probed. The tables
+    // don't know about castling, and cursed wins and blessed losses are reported as such.
+    pub fn probe_syzygy_wdl(&self, board: &Board) -> Option<WdlProbeResult> {
+        if !USE_SYZYGY || board.occupied_squares.count_ones() > 5 || board.state().castling_rights.iter().any(|rights| rights.kingside || rights.queenside) {
+            return None;
+        }
+        let bitboards = syzygy_bitboards(board);
+        self.syzygy
+            .probe_wdl(
+                bitboards[0],
+                bitboards[1],
+                bitboards[2],
+                bitboards[3],
+                bitboards[4],
+                bitboards[5],
+                bitboards[6],
+                bitboards[7],
+                flip_rank(board.state().en_passant_square.unwrap_or(56)) as u32,
+                board.side.value() == 0,
+            )
+            .ok()
+    }
+    // Evaluation once all captures have been resolved, used to tell quiet positions apart
+    pub fn quiescence(&mut self, board: &mut Board) -> i32 {
+        self.start_time = Instant::now();
+        self.quiescence_search(board, -MAX_EVAL, MAX_EVAL, 0)
     }
     pub fn extract_pv(&self) -> Vec<Move> {
         let mut pv = Vec::with_capacity(self.pv_lengths[0]);
@@ -601,6 +754,21 @@ impl Search {
         pv
     }
 
+    // Forget everything learned from previous games
+    pub fn new_game(&mut self) {
+        self.killer_moves = [[None; KILLER_MOVE_SLOTS]; MAX_DEPTH];
+        self.history = [[[0; 64]; 64]; 2];
+        self.transposition_table.clear();
+    }
+
+    // Replaces the hash table with an empty one of the given size, keeping the current one and its
+    // entries when the size is unchanged. Searches sharing the old table keep it.
+    pub fn resize_hash(&mut self, mb: usize) {
+        if self.transposition_table.entry_count() != mb_to_count(mb).max(1) {
+            self.transposition_table = Arc::new(TranspositionTable::with_size(mb));
+        }
+    }
+
     fn update_killer_moves(&mut self, mov: Move, ply: u32) {
         let ply = ply as usize;
         if !self.killer_moves[ply].contains(&Some(mov)) {
@@ -620,24 +788,8 @@ impl Search {
         return false;
     }
 
-    fn calculate_time(&mut self, board: &Board) -> u128 {
-        let half_moves_left = Search::remaining_half_moves(board.total_material) as u128;
-        let time_left = self.params.clock.time[board.side] + self.params.clock.inc[board.side] * half_moves_left / 2;
-        (time_left / half_moves_left) / 2
-    }
-
-    // Approximation for amount of half moves remaining
-    // See: http://facta.junis.ni.ac.rs/acar/acar200901/acar2009-07.pdf for more info
-    fn remaining_half_moves(material: u32) -> u32 {
-        match material {
-            0..20 => material + 10,
-            20..=60 => 3 * material / 8 + 22,
-            61.. => 5 * material / 4 - 30,
-        }
-    }
-
     pub fn should_quit(&self, ply: u32) -> bool {
-        if self.start_time.elapsed().as_millis() > self.max_time {
+        if self.time_manager.hard_limit_reached(self.start_time.elapsed().as_millis()) {
             return true;
         }
         if let Some(max_depth) = self.params.depth {
@@ -645,33 +797,72 @@ impl Search {
                 return true;
             }
         }
-        return self.should_quit.load(std::sync::atomic::Ordering::Relaxed);
+        // Only once the first iteration is done, so that there is always a move to play
+        if self.params.nodes.is_some_and(|max_nodes| self.result.nodes >= max_nodes && self.result.depth_reached > 0) {
+            return true;
+        }
+        self.should_quit.is_stopped()
+    }
+
+    fn search_info(&self, depth: u32, eval: i32, bound: Bound) -> SearchInfo {
+        // A failed aspiration window can leave no PV in the table, the last complete one stands in
+        let mut pv = self.extract_pv();
+        if pv.is_empty() {
+            pv = self.result.pv.clone();
+        }
+        SearchInfo {
+            depth,
+            seldepth: self.result.seldepth.max(depth),
+            eval,
+            bound,
+            time: self.start_time.elapsed(),
+            nodes: self.result.nodes,
+            hashfull: self.transposition_table.hashfull(),
+            tb_hits: self.result.tb_hits,
+            pv,
+            transpositions: self.result.transpositions,
+            transpositions_exact: self.result.transpositions_exact,
+            transpositions_lower: self.result.transpositions_lower,
+            transpositions_upper: self.result.transpositions_upper,
+        }
     }
+}
 
-    pub fn print_info(result: &SearchResult) {
-        print!(
-            "info depth {} score cp {} time {} nodes {} nps {} pv ",
-            result.depth_reached,
-            result.highest_eval,
-            result.time.as_millis(),
-            result.nodes,
-            ((result.nodes as f64 / result.time.as_nanos() as f64) * 1e9) as u32
-        );
-        for mov in result.pv.iter() {
-            print!("{} ", mov);
-        }
-        println!();
-        println!("tt {}", result.transpositions);
-        println!("tt_exact {}", result.transpositions_exact);
-        println!("tt_lower {}", result.transpositions_lower);
-        println!("tt_upper {}", result.transpositions_upper);
-        println!("transpositions {}", result.transpositions);
-        println!();
+// Score of a tablebase result for the side to move
+fn wdl_eval(wdl: WdlProbeResult, ply: u32) -> i32 {
+    match wdl {
+        WdlProbeResult::Loss => -TB_WIN + ply as i32,
+        WdlProbeResult::BlessedLoss => -MAX_EVAL + 10000 + ply as i32,
+        WdlProbeResult::Draw => 0,
+        WdlProbeResult::CursedWin => MAX_EVAL - 10000 - ply as i32,
+        WdlProbeResult::Win => TB_WIN - ply as i32,
     }
 }
 
+// Pieces in the order the tables expect, with squares numbered from a1
+fn syzygy_bitboards(board: &Board) -> [u64; 8] {
+    [
+        *board.side_squares[Side::White],
+        *board.side_squares[Side::Black],
+        *(board.piece_squares[Piece::WhiteKing] | board.piece_squares[Piece::BlackKing]),
+        *(board.piece_squares[Piece::WhiteQueen] | board.piece_squares[Piece::BlackQueen]),
+        *(board.piece_squares[Piece::WhiteRook] | board.piece_squares[Piece::BlackRook]),
+        *(board.piece_squares[Piece::WhiteBishop] | board.piece_squares[Piece::BlackBishop]),
+        *(board.piece_squares[Piece::WhiteKnight] | board.piece_squares[Piece::BlackKnight]),
+        *(board.piece_squares[Piece::WhitePawn] | board.piece_squares[Piece::BlackPawn]),
+    ]
+    .map(u64::swap_bytes)
+}
+
 impl Default for Search {
     fn default() -> Self {
+        Self::with_transposition_table(Arc::new(TranspositionTable::new()))
+    }
+}
+
+impl Search {
+    // Search using the given hash table, which can be shared with other searches
+    pub fn with_transposition_table(transposition_table: Arc<TranspositionTable>) -> Self {
         Self {
             params: SearchParams::default(),
             result: SearchResult::default(),
@@ -679,12 +870,16 @@ impl Default for Search {
             start_time: Instant::now(),
             pv_table: [[None; MAX_DEPTH]; MAX_DEPTH],
             pv_lengths: [0; MAX_DEPTH],
-            max_time: 0,
+            time_manager: TimeManager::default(),
+            root_move_nodes: [[0; 64]; 64],
             killer_moves: [[None; KILLER_MOVE_SLOTS]; MAX_DEPTH],
-            should_quit: Arc::new(AtomicBool::new(false)),
-            syzygy: pyrrhic_rs::TableBases::<Board>::new("./syzygy/tb345").unwrap(),
+            should_quit: Arc::new(StopSignal::default()),
+            transposition_table,
+            syzygy: SYZYGY.clone(),
             previous_static_eval: 0,
             history: [[[0; 64]; 64]; 2],
+            null_move_min_ply: 0,
+            listener: Box::new(UciListener::default()),
         }
     }
 }
@@ -695,7 +890,9 @@ pub struct SearchResult {
     pub pv: Vec<Move>,
     pub highest_eval: i32,
     pub depth_reached: u32,
+    pub seldepth: u32,
     pub nodes: u32,
+    pub tb_hits: u32,
     pub transpositions_exact: u32,
     pub transpositions_lower: u32,
     pub transpositions_upper: u32,
@@ -706,8 +903,9 @@ pub struct SearchResult {
 #[derive(PartialEq, Copy, Clone, Default)]
 pub struct SearchParams {
     pub depth: Option<u32>, // Maximum depth to search to
-    //pub nodes: usize,            // Maximum number of nodes to search
+    pub nodes: Option<u32>, // Maximum number of nodes to search
     pub move_time: u128,         // Maximum time per move to search
+    pub move_overhead: u128,     // Time lost per move to communication with the GUI
     pub clock: Clock,            // Time available for entire game
     pub search_mode: SearchMode, // Defines the mode to search in
     pub use_book: bool,
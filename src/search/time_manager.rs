@@ -0,0 +1,175 @@
+use crate::board::piece_move::Move;
+use crate::board::Board;
+use crate::search::search::{SearchMode, SearchParams};
+
+// Share of the per move budget that may be spent before no new iteration is started
+const SOFT_LIMIT_SCALE: f64 = 0.6;
+// Upper bound for a single move as a multiple of the per move budget
+const HARD_LIMIT_SCALE: f64 = 3.0;
+// Never use more than this share of the remaining clock on one move, so a slow move can't flag
+const MAX_CLOCK_FRACTION: f64 = 0.75;
+// Time reserved on top of the move overhead before dividing the clock between the remaining moves
+const SAFETY_MARGIN: u128 = 20;
+// Soft limit multipliers by the number of iterations the best move has stayed the same
+const STABILITY_SCALES: [f64; 5] = [2.2, 1.4, 1.1, 0.9, 0.8];
+
+/// Decides how long to think about a move. The soft limit is checked between iterations and
+/// adjusted by how settled the search looks, while the hard limit aborts the search outright.
+#[derive(Clone, Copy, Debug)]
+pub struct TimeManager {
+    pub soft_limit: u128,
+    pub hard_limit: u128,
+    base_soft_limit: u128,
+    is_clock: bool, // Only clock based limits are scaled, fixed move times are used in full
+    best_move: Option<Move>,
+    best_move_stability: usize,
+    previous_eval: Option<i32>,
+}
+
+impl TimeManager {
+    pub fn new(params: &SearchParams, board: &Board) -> Self {
+        let (soft_limit, hard_limit) = match params.search_mode {
+            SearchMode::Infinite => (u128::MAX, u128::MAX),
+            SearchMode::MoveTime => {
+                let move_time = params.move_time.saturating_sub(params.move_overhead).max(1);
+                (move_time, move_time)
+            }
+            SearchMode::Clock => Self::clock_limits(params, board),
+        };
+        Self {
+            soft_limit,
+            hard_limit,
+            base_soft_limit: soft_limit,
+            is_clock: params.search_mode == SearchMode::Clock,
+            best_move: None,
+            best_move_stability: 0,
+            previous_eval: None,
+        }
+    }
+
+    fn clock_limits(params: &SearchParams, board: &Board) -> (u128, u128) {
+        let time_left = params.clock.time[board.side];
+        let increment = params.clock.inc[board.side];
+
+        let moves_left = match params.clock.moves_to_go {
+            Some(moves_to_go) if moves_to_go > 0 => moves_to_go as u128,
+            _ => (Self::remaining_half_moves(board.total_material) / 2).max(1) as u128,
+        };
+
+        // Each remaining move pays the move overhead, so reserve it for a few of them up front
+        let reserved = params.move_overhead * moves_left.min(5) + SAFETY_MARGIN;
+        let available = time_left.saturating_sub(reserved).max(1);
+        let budget = (available / moves_left + increment * 3 / 4) as f64;
+
+        let max_time = (available as f64 * MAX_CLOCK_FRACTION).max(1.0);
+        let hard_limit = (budget * HARD_LIMIT_SCALE).min(max_time);
+        let soft_limit = (budget * SOFT_LIMIT_SCALE).min(hard_limit);
+        (soft_limit as u128, hard_limit as u128)
+    }
+
+    // Approximation for amount of half moves remaining
+    // See: http://facta.junis.ni.ac.rs/acar/acar200901/acar2009-07.pdf for more info
+    fn remaining_half_moves(material: u32) -> u32 {
+        match material {
+            0..20 => material + 10,
+            20..=60 => 3 * material / 8 + 22,
+            61.. => 5 * material / 4 - 30,
+        }
+    }
+
+    // Rescales the soft limit after a completed iteration. Thinks longer when the best move keeps
+    // changing, when the score drops, and when the best move took little of the search effort.
+    pub fn update(&mut self, best_move: Option<Move>, eval: i32, best_move_nodes: u32, total_nodes: u32) {
+        if !self.is_clock {
+            return;
+        }
+
+        if best_move == self.best_move {
+            self.best_move_stability = (self.best_move_stability + 1).min(STABILITY_SCALES.len() - 1);
+        } else {
+            self.best_move_stability = 0;
+        }
+        self.best_move = best_move;
+        let stability_scale = STABILITY_SCALES[self.best_move_stability];
+
+        let score_drop = self.previous_eval.map_or(0, |previous_eval| previous_eval - eval);
+        let score_scale = 1.0 + score_drop.clamp(0, 100) as f64 / 200.0;
+        self.previous_eval = Some(eval);
+
+        let best_move_fraction = if total_nodes == 0 { 1.0 } else { best_move_nodes as f64 / total_nodes as f64 };
+        let node_scale = (1.5 - best_move_fraction) * 1.35;
+
+        let soft_limit = self.base_soft_limit as f64 * stability_scale * score_scale * node_scale;
+        self.soft_limit = (soft_limit as u128).min(self.hard_limit);
+    }
+
+    pub fn soft_limit_reached(&self, elapsed: u128) -> bool {
+        elapsed >= self.soft_limit
+    }
+    pub fn hard_limit_reached(&self, elapsed: u128) -> bool {
+        elapsed > self.hard_limit
+    }
+}
+
+impl Default for TimeManager {
+    fn default() -> Self {
+        Self {
+            soft_limit: u128::MAX,
+            hard_limit: u128::MAX,
+            base_soft_limit: u128::MAX,
+            is_clock: false,
+            best_move: None,
+            best_move_stability: 0,
+            previous_eval: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::piece_move::MoveType;
+
+    fn clock_params(time: u128, inc: u128, moves_to_go: Option<usize>) -> SearchParams {
+        let mut params = SearchParams {
+            search_mode: SearchMode::Clock,
+            move_overhead: 10,
+            ..Default::default()
+        };
+        params.clock.time = [time; 2];
+        params.clock.inc = [inc; 2];
+        params.clock.moves_to_go = moves_to_go;
+        params
+    }
+
+    #[test]
+    fn stays_within_clock() {
+        let board = Board::start_pos();
+        for (time, inc) in [(10000, 100), (500, 100), (50, 100), (60000, 0)] {
+            let time_manager = TimeManager::new(&clock_params(time, inc, None), &board);
+            assert!(time_manager.soft_limit <= time_manager.hard_limit);
+            assert!(time_manager.hard_limit < time.max(2), "hard limit {} with {}ms on the clock", time_manager.hard_limit, time);
+        }
+    }
+
+    #[test]
+    fn uses_moves_to_go() {
+        let board = Board::start_pos();
+        let sudden_death = TimeManager::new(&clock_params(60000, 0, None), &board);
+        let one_move_left = TimeManager::new(&clock_params(60000, 0, Some(1)), &board);
+        assert!(one_move_left.hard_limit > sudden_death.hard_limit);
+    }
+
+    #[test]
+    fn stable_best_move_shortens_search() {
+        let board = Board::start_pos();
+        let mut time_manager = TimeManager::new(&clock_params(60000, 0, None), &board);
+        let mov = Some(Move::new(52, 36, MoveType::DoublePush));
+        time_manager.update(mov, 20, 900, 1000);
+        let unstable_limit = time_manager.soft_limit;
+        for _ in 0..4 {
+            time_manager.update(mov, 20, 900, 1000);
+        }
+        assert!(time_manager.soft_limit < unstable_limit);
+    }
+}
@@ -1,86 +1,87 @@
-use ctor::ctor;
-
 use crate::board::piece_move::Move;
-use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicU64, Ordering};
 
-pub const TABLE_SIZE: usize = mb_to_count(256);
+pub const DEFAULT_HASH_MB: usize = 256;
+pub const MAX_HASH_MB: usize = 65536;
+const HASHFULL_SAMPLE_SIZE: usize = 1000;
 
 pub const fn mb_to_count(mb: usize) -> usize {
     (mb * 1_004_858) / std::mem::size_of::<TranspositionEntry>()
 }
 
-pub static mut TRANSPOSITION_TABLE: MaybeUninit<TranspositionTable> = MaybeUninit::uninit();
-
-// For global static access - inherently unsafe
-pub fn global_store(entry: TranspositionEntry) {
-    unsafe {
-        (*TRANSPOSITION_TABLE.as_mut_ptr()).store(entry);
-    }
-}
-
-pub fn global_probe(hash: u64) -> Option<TranspositionEntry> {
-    unsafe { (*TRANSPOSITION_TABLE.as_mut_ptr()).probe(hash) }
+// An entry packed into one word, stored next to its hash xored with that word. Searches sharing the
+// table through an `Arc` don't lock it, so a slot read while another thread writes it may mix two entries, which
+// then fails the hash check instead of being returned with the wrong data. A zero word is empty.
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
 }
 
-// Then in your initialization function with the ctor library:
-#[ctor]
-fn initialize_table() {
-    unsafe {
-        TRANSPOSITION_TABLE.write(TranspositionTable::new());
-    }
-}
-
-#[derive(Clone)]
 pub struct TranspositionTable {
-    pub table: Box<[Option<TranspositionEntry>; TABLE_SIZE]>,
+    table: Box<[Slot]>,
 }
 
 impl TranspositionTable {
     pub fn new() -> Self {
-        Self {
-            table: vec![None; TABLE_SIZE].into_boxed_slice().try_into().unwrap(),
-        }
+        Self::with_size(DEFAULT_HASH_MB)
+    }
+    // The memory is requested zeroed, so the pages are only committed once entries are stored
+    pub fn with_size(mb: usize) -> Self {
+        let table = Box::<[Slot]>::new_zeroed_slice(mb_to_count(mb).max(1));
+        // Safe as a zeroed slot is two zeroed atomics, which is an empty slot
+        Self { table: unsafe { table.assume_init() } }
     }
-    pub fn store(&mut self, entry: TranspositionEntry) {
-        let index = self.get_index(entry.hash);
-        self.table[index] = Some(entry);
-        //if let Some(existing) = self.table[index] {
-        //        self.table[index] = Some(entry);
-        //} else {
-        //    self.table[index] = Some(entry);
-        //}
+    pub fn entry_count(&self) -> usize {
+        self.table.len()
+    }
+    pub fn store(&self, entry: TranspositionEntry) {
+        let slot = &self.table[self.get_index(entry.hash)];
+        let data = entry.pack();
+        slot.key.store(entry.hash ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
     }
 
+    // The entry in the slot of the hash, which may belong to a different position
     pub fn probe(&self, hash: u64) -> Option<TranspositionEntry> {
-        let index = self.get_index(hash);
-        self.table[index]
+        let slot = &self.table[self.get_index(hash)];
+        let data = slot.data.load(Ordering::Relaxed);
+        let key = slot.key.load(Ordering::Relaxed);
+        (data != 0).then(|| TranspositionEntry::unpack(key ^ data, data))
     }
 
-    pub fn clear(&mut self) {
-        *self.table = [None; TABLE_SIZE]
+    pub fn clear(&self) {
+        for slot in self.table.iter() {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
     }
 
     pub fn filled_count(&self) -> usize {
-        self.table.iter().filter(|entry| entry.is_some()).count()
+        self.table.iter().filter(|slot| slot.data.load(Ordering::Relaxed) != 0).count()
     }
 
     pub fn filled_percentage(&self) -> f64 {
         let filled = self.filled_count();
-        (filled as f64 / TABLE_SIZE as f64) * 100.0
+        (filled as f64 / self.table.len() as f64) * 100.0
+    }
+
+    // Permille of the table in use, estimated from a sample since entries are spread uniformly
+    pub fn hashfull(&self) -> usize {
+        let filled = self.table.iter().take(HASHFULL_SAMPLE_SIZE).filter(|slot| slot.data.load(Ordering::Relaxed) != 0).count();
+        filled * 1000 / HASHFULL_SAMPLE_SIZE
     }
 
     fn get_index(&self, hash: u64) -> usize {
-        (hash % (TABLE_SIZE) as u64) as usize
+        (hash % self.table.len() as u64) as usize
     }
 }
 
-//impl Default for TranspositionTable {
-//    fn default() -> Self {
-//        Self {
-//            table: vec![None; TABLE_SIZE].into_boxed_slice().try_into().unwrap(),
-//        }
-//    }
-//}
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 #[derive(Clone, Copy, Debug)]
 pub struct TranspositionEntry {
@@ -95,6 +96,19 @@ impl TranspositionEntry {
     pub fn new(depth: u8, eval: i16, best_move: Move, node_type: Bound, hash: u64) -> Self {
         Self { depth, eval, best_move, node_type, hash }
     }
+
+    // Depth, eval, move and bound in the low 42 bits, with bit 48 set so no entry packs to zero
+    fn pack(&self) -> u64 {
+        self.depth as u64 | (self.eval as u16 as u64) << 8 | (self.best_move.bits() as u64) << 24 | (self.node_type as u64) << 40 | 1 << 48
+    }
+    fn unpack(hash: u64, data: u64) -> Self {
+        let node_type = match (data >> 40) & 0b11 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+        Self::new(data as u8, (data >> 8) as u16 as i16, Move::from_bits((data >> 24) as u16), node_type, hash)
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -0,0 +1,127 @@
+/// Results of a match between two engines, counted in pairs of games played from the same opening
+/// with colours swapped, from the first engine's point of view.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct MatchResults {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+    pub pairs: [u32; 5], // Number of pairs scoring 0, 0.5, 1, 1.5 and 2 points
+}
+
+/// Elo difference the match is testing for, with the accepted rates of false positives and
+/// false negatives.
+#[derive(Clone, Copy, Debug)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Sprt {
+    pub fn new(elo0: f64, elo1: f64) -> Self {
+        Self { elo0, elo1, alpha: 0.05, beta: 0.05 }
+    }
+    // The test stops once the log likelihood ratio leaves these
+    pub fn bounds(&self) -> (f64, f64) {
+        ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln())
+    }
+}
+
+// Expected score of a side with this Elo advantage
+fn expected_score(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+fn elo_difference(score: f64) -> f64 {
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+impl MatchResults {
+    // Scores of the first engine in both games of a pair, 0.0, 0.5 or 1.0 each
+    pub fn add_pair(&mut self, scores: [f64; 2]) {
+        for score in scores {
+            match score {
+                1.0 => self.wins += 1,
+                0.5 => self.draws += 1,
+                _ => self.losses += 1,
+            }
+        }
+        self.pairs[((scores[0] + scores[1]) * 2.0) as usize] += 1;
+    }
+
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    // Mean score per game and the variance of the mean score of a pair
+    fn score_and_variance(&self) -> Option<(f64, f64)> {
+        let pairs: u32 = self.pairs.iter().sum();
+        if pairs == 0 {
+            return None;
+        }
+        let frequencies = self.pairs.map(|count| count as f64 / pairs as f64);
+        let score: f64 = frequencies.iter().enumerate().map(|(points, frequency)| frequency * points as f64 / 4.0).sum();
+        let variance = frequencies.iter().enumerate().map(|(points, frequency)| frequency * (points as f64 / 4.0 - score).powi(2)).sum();
+        Some((score, variance))
+    }
+
+    // Elo difference and the margin of its 95% confidence interval, None while either side has
+    // scored every point
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        let (score, variance) = self.score_and_variance()?;
+        if score <= 0.0 || score >= 1.0 {
+            return None;
+        }
+        let pairs: u32 = self.pairs.iter().sum();
+        let margin = 1.96 * (variance / pairs as f64).sqrt();
+        let bound = |score: f64| elo_difference(score.clamp(1e-6, 1.0 - 1e-6));
+        Some((elo_difference(score), (bound(score + margin) - bound(score - margin)) / 2.0))
+    }
+
+    // Log likelihood ratio of the generalized SPRT on the pair scores, None while every pair
+    // scored the same
+    pub fn llr(&self, sprt: &Sprt) -> Option<f64> {
+        let (score, variance) = self.score_and_variance()?;
+        if variance == 0.0 {
+            return None;
+        }
+        let pairs: u32 = self.pairs.iter().sum();
+        let (score0, score1) = (expected_score(sprt.elo0), expected_score(sprt.elo1));
+        Some(pairs as f64 * (score1 - score0) * (2.0 * score - score0 - score1) / (2.0 * variance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_elo_and_llr() {
+        let sprt = Sprt::new(0.0, 5.0);
+        let (lower, upper) = sprt.bounds();
+        assert!((lower + 2.944).abs() < 1e-3 && (upper - 2.944).abs() < 1e-3);
+
+        let mut results = MatchResults::default();
+        assert_eq!((results.elo(), results.llr(&sprt)), (None, None));
+        results.add_pair([0.5, 0.5]);
+        assert_eq!(results.llr(&sprt), None);
+        results.add_pair([1.0, 0.0]);
+        results.add_pair([1.0, 0.5]);
+        results.add_pair([0.0, 0.5]);
+        assert_eq!((results.wins, results.draws, results.losses, results.games()), (2, 4, 2, 8));
+        assert_eq!(results.pairs, [0, 1, 2, 1, 0]);
+        let (elo, margin) = results.elo().unwrap();
+        assert!(elo.abs() < 1e-9 && margin > 0.0);
+        assert!(results.llr(&sprt).unwrap() < 0.0);
+
+        // Winning every other pair is about 191 Elo, enough to accept H1 after a few hundred games
+        let mut results = MatchResults::default();
+        for _ in 0..100 {
+            results.add_pair([1.0, 1.0]);
+            results.add_pair([0.5, 0.5]);
+        }
+        assert!((results.elo().unwrap().0 - 190.85).abs() < 0.1);
+        assert!(results.llr(&sprt).unwrap() > upper);
+    }
+}